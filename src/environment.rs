@@ -1,53 +1,178 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
-use crate::{lox_error, lox_type::LoxType};
+use crate::{LoxError, lox_type::LoxType, runtime_error};
 
 #[derive(Clone)]
 pub struct Environment {
     pub enclosing: Option<Box<Environment>>,
-    pub values: HashMap<String, LoxType>,
+    /// Each value is paired with whether it was declared `const`.
+    pub values: Arc<Mutex<HashMap<String, (LoxType, bool)>>>,
 }
 
 impl Environment {
     pub fn new(enclosing: Option<Environment>, values: HashMap<String, LoxType>) -> Self {
         Self {
             enclosing: enclosing.map(Box::new),
-            values,
+            values: Arc::new(Mutex::new(
+                values
+                    .into_iter()
+                    .map(|(name, value)| (name, (value, false)))
+                    .collect(),
+            )),
         }
     }
 
-    pub fn reset(&mut self, with: &Environment) {
-        self.enclosing = with.enclosing.clone();
-        self.values = with.values.clone();
+    pub fn define(&mut self, name: String, value: LoxType) {
+        self.values.lock().unwrap().insert(name, (value, false));
     }
 
-    pub fn define(&mut self, name: String, value: LoxType) {
-        self.values.insert(name, value);
+    /// Declares `name` in this scope, refusing to replace a constant declared here before.
+    pub fn declare(
+        &mut self,
+        name: String,
+        value: LoxType,
+        is_const: bool,
+        line: usize,
+    ) -> Result<(), LoxError> {
+        let mut values = self.values.lock().unwrap();
+
+        if let Some((_, true)) = values.get(&name) {
+            return Err(runtime_error!(line, "Cannot redefine constant '{}'.", name));
+        }
+
+        values.insert(name, (value, is_const));
+
+        Ok(())
     }
 
-    pub fn get(&self, name: &String) -> &LoxType {
-        if let Some(value) = self.values.get(name) {
-            return value;
+    pub fn get(&self, name: &String, line: usize) -> Result<LoxType, LoxError> {
+        if let Some((value, _)) = self.values.lock().unwrap().get(name) {
+            return Ok(value.clone());
         }
 
         if let Some(enclosing) = &self.enclosing {
-            return enclosing.get(name);
+            return enclosing.get(name, line);
+        }
+
+        Err(runtime_error!(line, "Undefined variable '{}'.", name))
+    }
+
+    pub fn get_at(
+        &self,
+        depth: Option<usize>,
+        name: &String,
+        line: usize,
+    ) -> Result<LoxType, LoxError> {
+        if let Some((value, _)) = self.scope_at(depth).values.lock().unwrap().get(name) {
+            return Ok(value.clone());
         }
 
-        lox_error!("Undefined variable '{}'.", name);
+        Err(runtime_error!(line, "Undefined variable '{}'.", name))
     }
 
-    pub fn assign(&mut self, name: String, value: LoxType) {
-        if let Some(_) = self.values.get(&name) {
-            self.values.insert(name.clone(), value);
-            return;
+    pub fn assign_at(
+        &mut self,
+        depth: Option<usize>,
+        name: String,
+        value: LoxType,
+        line: usize,
+    ) -> Result<(), LoxError> {
+        if let Some((slot, is_const)) = self.scope_at(depth).values.lock().unwrap().get_mut(&name) {
+            if *is_const {
+                return Err(runtime_error!(
+                    line,
+                    "Cannot assign to constant '{}'.",
+                    name
+                ));
+            }
+
+            *slot = value;
+            return Ok(());
         }
 
-        if let Some(ref mut enclosing) = self.enclosing {
-            enclosing.assign(name.clone(), value);
-            return;
+        Err(runtime_error!(line, "Undefined variable '{}'.", name))
+    }
+
+    fn scope_at(&self, depth: Option<usize>) -> &Environment {
+        let mut scope = self;
+
+        match depth {
+            Some(depth) => {
+                for _ in 0..depth {
+                    scope = scope
+                        .enclosing
+                        .as_deref()
+                        .expect("Resolved scope depth exceeds the environment chain.");
+                }
+            }
+            None => {
+                while let Some(enclosing) = scope.enclosing.as_deref() {
+                    scope = enclosing;
+                }
+            }
         }
 
-        lox_error!("Undefined variable '{}'", name);
+        scope
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::error;
+
+    #[test]
+    fn refuses_to_redeclare_constants() {
+        let mut env = Environment::new(None, HashMap::new());
+
+        env.declare("x".to_string(), LoxType::Integer(1), true, 1)
+            .unwrap();
+
+        assert_eq!(
+            env.declare("x".to_string(), LoxType::Integer(2), false, 2)
+                .unwrap_err()
+                .to_string(),
+            "[line 2] Error: Cannot redefine constant 'x'."
+        );
+        assert_eq!(env.get(&"x".to_string(), 3).unwrap().to_string(), "1");
+    }
+
+    #[test]
+    fn allows_redeclaring_variables() {
+        let mut env = Environment::new(None, HashMap::new());
+
+        env.declare("x".to_string(), LoxType::Integer(1), false, 1)
+            .unwrap();
+        env.declare("x".to_string(), LoxType::Integer(2), true, 2)
+            .unwrap();
+
+        assert_eq!(env.get(&"x".to_string(), 3).unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn reports_undefined_variable() {
+        assert_eq!(
+            error("print nope;"),
+            "[line 1] Error: Undefined variable 'nope'."
+        );
+    }
+
+    #[test]
+    fn rejects_reassigning_constants() {
+        assert_eq!(
+            error("const x = 1; x = 2;"),
+            "[line 1] Error: Cannot assign to constant 'x'."
+        );
+        assert_eq!(
+            error("const x = 1; x++;"),
+            "[line 1] Error: Cannot assign to constant 'x'."
+        );
+        assert_eq!(
+            error("const x = 1;\nvar x = 2;"),
+            "[line 2] Error: Cannot redefine constant 'x'."
+        );
     }
 }
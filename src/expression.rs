@@ -1,303 +1,1158 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
 use crate::{
+    LoxError,
     environment::Environment,
-    lox_error,
-    lox_type::{LoxNumber, LoxString, LoxType},
+    lox_array, lox_string,
+    lox_type::{
+        LoxFunction, LoxInstance, LoxInteger, LoxNumber, LoxString, LoxType, call_function,
+        format_number,
+    },
+    runtime_error,
+    statement::FunctionStatement,
     token::{Keyword, Token},
 };
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct AssignExpr {
     pub name: String,
     pub value: Box<Expr>,
+    pub line: usize,
+    pub depth: Option<usize>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct ArrayExpr {
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct CallExpr {
     pub callee: Box<Expr>,
     pub paren: Token,
     pub arguments: Vec<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct FunctionExpr {
+    pub function: FunctionStatement,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct GetExpr {
     pub object: Box<Expr>,
     pub name: Token,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct OptionalGetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct NilCoalesceExpr {
+    pub left: Box<Expr>,
+    pub right: Box<Expr>,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct GroupingExpr {
     pub expression: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct IndexSetExpr {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone, PartialEq)]
 pub enum LiteralExprType {
     Identifier(Keyword),
     String(LoxString),
     Number(LoxNumber),
-    EOF,
+    Integer(LoxInteger),
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct LiteralExpr {
     pub value: LiteralExprType,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct LogicalExpr {
     pub left: Box<Expr>,
-    pub operator: Token,
+    pub operator: LogicalOp,
     pub right: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct MapExpr {
+    pub brace: Token,
+    pub pairs: Vec<(Expr, Expr)>,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct SetExpr {
     pub object: Box<Expr>,
     pub name: Token,
     pub value: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct SpreadExpr {
+    pub token: Token,
+    pub expression: Box<Expr>,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct SuperExpr {
     pub keyword: Token,
     pub method: Token,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct TernaryExpr {
     pub condition: Box<Expr>,
     pub trueish: Box<Expr>,
     pub falseish: Box<Expr>,
+    pub line: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct ThisExpr {
     pub keyword: Token,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct UnaryExpr {
     pub operator: Token,
     pub right: Box<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+pub struct UpdateExpr {
+    pub target: Box<Expr>,
+    pub operator: Token,
+    pub prefix: bool,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct VariableExpr {
     pub name: String,
+    pub line: usize,
+    pub depth: Option<usize>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Expr {
+    Array(ArrayExpr),
     Assign(AssignExpr),
     Binary(BinaryExpr),
     Call(CallExpr),
+    Function(FunctionExpr),
     Get(GetExpr),
     Grouping(GroupingExpr),
+    Index(IndexExpr),
+    IndexSet(IndexSetExpr),
     Literal(LiteralExpr),
     Logical(LogicalExpr),
+    Map(MapExpr),
+    NilCoalesce(NilCoalesceExpr),
+    OptionalGet(OptionalGetExpr),
     Set(SetExpr),
+    Spread(SpreadExpr),
     Super(SuperExpr),
     Ternary(TernaryExpr),
     This(ThisExpr),
     Unary(UnaryExpr),
+    Update(UpdateExpr),
     Variable(VariableExpr),
 }
 
+impl std::fmt::Display for LiteralExprType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiteralExprType::Identifier(id) => write!(f, "{}", id),
+            LiteralExprType::String(str) => write!(f, "{:?}", str),
+            LiteralExprType::Number(num) => write!(f, "{}", format_number(*num)),
+            LiteralExprType::Integer(num) => write!(f, "{}", num),
+        }
+    }
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Array(array_expr) => {
+                write!(f, "(array")?;
+
+                for element in &array_expr.elements {
+                    write!(f, " {}", element)?;
+                }
+
+                write!(f, ")")
+            }
+            Expr::Assign(assign_expr) => {
+                write!(f, "(= {} {})", assign_expr.name, assign_expr.value)
+            }
+            Expr::Binary(binary_expr) => write!(
+                f,
+                "({} {} {})",
+                binary_expr.operator.lexeme(),
+                binary_expr.left,
+                binary_expr.right
+            ),
+            Expr::Call(call_expr) => {
+                write!(f, "(call {}", call_expr.callee)?;
+
+                for argument in &call_expr.arguments {
+                    write!(f, " {}", argument)?;
+                }
+
+                write!(f, ")")
+            }
+            Expr::Function(function_expr) => write!(f, "{}", function_expr.function),
+            Expr::Get(get_expr) => {
+                write!(f, "(. {} {})", get_expr.object, get_expr.name.lexeme())
+            }
+            Expr::Grouping(grouping_expr) => write!(f, "(group {})", grouping_expr.expression),
+            Expr::Index(index_expr) => {
+                write!(f, "([] {} {})", index_expr.object, index_expr.index)
+            }
+            Expr::IndexSet(index_set_expr) => write!(
+                f,
+                "([]= {} {} {})",
+                index_set_expr.object, index_set_expr.index, index_set_expr.value
+            ),
+            Expr::Literal(literal_expr) => write!(f, "{}", literal_expr.value),
+            Expr::Logical(logical_expr) => {
+                let operator = match logical_expr.operator {
+                    LogicalOp::And => "and",
+                    LogicalOp::Or => "or",
+                };
+
+                write!(
+                    f,
+                    "({} {} {})",
+                    operator, logical_expr.left, logical_expr.right
+                )
+            }
+            Expr::Map(map_expr) => {
+                write!(f, "(map")?;
+
+                for (key, value) in &map_expr.pairs {
+                    write!(f, " ({} {})", key, value)?;
+                }
+
+                write!(f, ")")
+            }
+            Expr::NilCoalesce(nil_coalesce_expr) => write!(
+                f,
+                "(?? {} {})",
+                nil_coalesce_expr.left, nil_coalesce_expr.right
+            ),
+            Expr::OptionalGet(optional_get_expr) => write!(
+                f,
+                "(?. {} {})",
+                optional_get_expr.object,
+                optional_get_expr.name.lexeme()
+            ),
+            Expr::Set(set_expr) => write!(
+                f,
+                "(.= {} {} {})",
+                set_expr.object,
+                set_expr.name.lexeme(),
+                set_expr.value
+            ),
+            Expr::Spread(spread_expr) => write!(f, "(... {})", spread_expr.expression),
+            Expr::Super(super_expr) => write!(f, "(super {})", super_expr.method.lexeme()),
+            Expr::Ternary(ternary_expr) => write!(
+                f,
+                "(?: {} {} {})",
+                ternary_expr.condition, ternary_expr.trueish, ternary_expr.falseish
+            ),
+            Expr::This(_) => write!(f, "this"),
+            Expr::Unary(unary_expr) => {
+                write!(f, "({} {})", unary_expr.operator.lexeme(), unary_expr.right)
+            }
+            Expr::Update(update_expr) => {
+                let fixity = if update_expr.prefix { "pre" } else { "post" };
+
+                write!(
+                    f,
+                    "({}{} {})",
+                    fixity,
+                    update_expr.operator.lexeme(),
+                    update_expr.target
+                )
+            }
+            Expr::Variable(variable_expr) => write!(f, "{}", variable_expr.name),
+        }
+    }
+}
+
+fn concat_strings(left: &str, right: &str) -> LoxString {
+    let mut result = String::with_capacity(left.len() + right.len());
+    result.push_str(left);
+    result.push_str(right);
+
+    LoxString::from(result)
+}
+
+fn numeric_operands(left: &LoxType, right: &LoxType) -> Option<(LoxNumber, LoxNumber)> {
+    Some((left.as_number()?, right.as_number()?))
+}
+
+// Strings compare lexicographically; a comparison involving NaN has no ordering.
+fn compare(left: &LoxType, right: &LoxType, line: usize) -> Result<Option<Ordering>, LoxError> {
+    if let (LoxType::String(ls), LoxType::String(rs)) = (left, right) {
+        return Ok(Some(ls.cmp(rs)));
+    }
+
+    let Some((ln, rn)) = numeric_operands(left, right) else {
+        return Err(runtime_error!(
+            line,
+            "Operands must be two numbers or two strings."
+        ));
+    };
+
+    Ok(ln.partial_cmp(&rn))
+}
+
+// Two integers stay integers unless the result overflows, which falls back to float math.
+fn arithmetic(
+    left: &LoxType,
+    right: &LoxType,
+    integer_op: fn(LoxInteger, LoxInteger) -> Option<LoxInteger>,
+    float_op: fn(LoxNumber, LoxNumber) -> LoxNumber,
+) -> Option<LoxType> {
+    if let (LoxType::Integer(ln), LoxType::Integer(rn)) = (left, right)
+        && let Some(result) = integer_op(*ln, *rn)
+    {
+        return Some(LoxType::Integer(result));
+    }
+
+    let (ln, rn) = numeric_operands(left, right)?;
+
+    Some(LoxType::Number(float_op(ln, rn)))
+}
+
+fn integer_operands(left: LoxType, right: LoxType, line: usize) -> Result<(i64, i64), LoxError> {
+    match (left, right) {
+        (LoxType::Number(ln), LoxType::Number(rn)) => Ok((ln as i64, rn as i64)),
+        (LoxType::Integer(ln), LoxType::Integer(rn)) => Ok((ln, rn)),
+        (LoxType::Integer(ln), LoxType::Number(rn)) => Ok((ln, rn as i64)),
+        (LoxType::Number(ln), LoxType::Integer(rn)) => Ok((ln as i64, rn)),
+        _ => Err(runtime_error!(line, "Operands must be numbers.")),
+    }
+}
+
+fn shift_amount(amount: i64, line: usize) -> Result<u32, LoxError> {
+    if !(0..64).contains(&amount) {
+        return Err(runtime_error!(
+            line,
+            "Shift amount must be between 0 and 63."
+        ));
+    }
+
+    Ok(amount as u32)
+}
+
+fn map_key(key: LoxType, line: usize) -> Result<String, LoxError> {
+    let LoxType::String(key) = key else {
+        return Err(runtime_error!(line, "Map keys must be strings."));
+    };
+
+    Ok(key.to_string())
+}
+
+fn get_property(object: LoxType, name: &Token) -> Result<LoxType, LoxError> {
+    let line = name.line();
+
+    let Token::Keyword(k) = name else {
+        unreachable!()
+    };
+
+    match object {
+        LoxType::String(s) => lox_string::get(&s, &k.keyword.lexeme(), line),
+        LoxType::Array(a) => lox_array::get(&a, &k.keyword.lexeme(), line),
+        LoxType::Instance(i) => LoxInstance::get(&i, &k.keyword.lexeme(), line),
+        _ => Err(runtime_error!(line, "Only instances have properties.")),
+    }
+}
+
+// `++` and `--` step a number by one, keeping integers integral.
+fn step(value: &LoxType, operator: &Token) -> Result<LoxType, LoxError> {
+    let one = LoxType::Integer(1);
+
+    let result = match operator {
+        Token::PlusPlus(_) => arithmetic(value, &one, LoxInteger::checked_add, |ln, rn| ln + rn),
+        Token::MinusMinus(_) => arithmetic(value, &one, LoxInteger::checked_sub, |ln, rn| ln - rn),
+        _ => unreachable!(),
+    };
+
+    result.ok_or_else(|| runtime_error!(operator.line(), "Operand must be a number."))
+}
+
+fn array_index(items: &[LoxType], index: LoxType, line: usize) -> Result<usize, LoxError> {
+    let Some(n) = index.as_number() else {
+        return Err(runtime_error!(line, "Array index must be a number."));
+    };
+
+    if n < 0. || n.fract() != 0. {
+        return Err(runtime_error!(
+            line,
+            "Array index must be a non-negative integer."
+        ));
+    }
+
+    let index = n as usize;
+
+    if index >= items.len() {
+        return Err(runtime_error!(
+            line,
+            "Array index {} is out of bounds for an array of length {}.",
+            index,
+            items.len()
+        ));
+    }
+
+    Ok(index)
+}
+
 impl Expr {
-    pub fn eval(&self, env: &mut Environment) -> LoxType {
+    pub fn eval(&self, env: &mut Environment) -> Result<LoxType, LoxError> {
         match self {
+            Expr::Array(array_expr) => {
+                let elements = array_expr
+                    .elements
+                    .iter()
+                    .map(|element| element.eval(env))
+                    .collect::<Result<Vec<LoxType>, LoxError>>()?;
+
+                Ok(LoxType::Array(Arc::new(Mutex::new(elements))))
+            }
             Expr::Assign(assign_expr) => {
-                let value = assign_expr.value.eval(env);
+                let value = assign_expr.value.eval(env)?;
 
-                env.assign(assign_expr.name.clone(), value.clone());
-                return value;
+                env.assign_at(
+                    assign_expr.depth,
+                    assign_expr.name.clone(),
+                    value.clone(),
+                    assign_expr.line,
+                )?;
+                Ok(value)
             }
             Expr::Binary(binary_expr) => {
-                let left = binary_expr.left.eval(env);
-                let right = binary_expr.right.eval(env);
+                let left = binary_expr.left.eval(env)?;
+                let right = binary_expr.right.eval(env)?;
 
                 let result = match &binary_expr.operator {
-                    Token::Greater(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Boolean(ln > rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot compare NaNs",
-                            binary_expr.operator.line()
-                        ),
-                    },
-                    Token::GreaterEqual(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Boolean(ln >= rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot compare NaNs",
-                            binary_expr.operator.line()
-                        ),
-                    },
-                    Token::Less(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Boolean(ln < rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot compare NaNs",
-                            binary_expr.operator.line()
-                        ),
-                    },
-                    Token::LessEqual(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Boolean(ln <= rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot compare NaNs",
-                            binary_expr.operator.line()
-                        ),
-                    },
+                    Token::Greater(_) => {
+                        let ordering = compare(&left, &right, binary_expr.operator.line())?;
+                        LoxType::Boolean(ordering == Some(Ordering::Greater))
+                    }
+                    Token::GreaterEqual(_) => {
+                        let ordering = compare(&left, &right, binary_expr.operator.line())?;
+                        LoxType::Boolean(matches!(
+                            ordering,
+                            Some(Ordering::Greater | Ordering::Equal)
+                        ))
+                    }
+                    Token::Less(_) => {
+                        let ordering = compare(&left, &right, binary_expr.operator.line())?;
+                        LoxType::Boolean(ordering == Some(Ordering::Less))
+                    }
+                    Token::LessEqual(_) => {
+                        let ordering = compare(&left, &right, binary_expr.operator.line())?;
+                        LoxType::Boolean(matches!(ordering, Some(Ordering::Less | Ordering::Equal)))
+                    }
                     Token::BangEqual(_) => LoxType::Boolean(left != right),
                     Token::EqualEqual(_) => LoxType::Boolean(left == right),
-                    Token::Minus(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Number(ln - rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot subtract NaNs",
-                            binary_expr.operator.line()
-                        ),
-                    },
+                    Token::Minus(_) => {
+                        match arithmetic(&left, &right, LoxInteger::checked_sub, |ln, rn| ln - rn) {
+                            Some(result) => result,
+                            None => {
+                                return Err(runtime_error!(
+                                    binary_expr.operator.line(),
+                                    "Operands must be numbers."
+                                ));
+                            }
+                        }
+                    }
                     Token::Plus(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Number(ln + rn),
-                        (LoxType::String(ls), LoxType::String(rs)) => LoxType::String(ls + &rs),
-                        (LoxType::String(ls), LoxType::Number(rn)) => {
-                            LoxType::String(ls + &rn.to_string())
+                        (LoxType::String(ls), LoxType::String(rs)) => {
+                            LoxType::String(concat_strings(&ls, &rs))
+                        }
+                        (LoxType::String(ls), rn @ (LoxType::Number(_) | LoxType::Integer(_))) => {
+                            LoxType::String(concat_strings(&ls, &rn.to_string()))
                         }
-                        (LoxType::Number(ln), LoxType::String(rs)) => {
-                            LoxType::String(ln.to_string() + &rs)
+                        (ln @ (LoxType::Number(_) | LoxType::Integer(_)), LoxType::String(rs)) => {
+                            LoxType::String(concat_strings(&ln.to_string(), &rs))
+                        }
+                        (left, right) => {
+                            match arithmetic(&left, &right, LoxInteger::checked_add, |ln, rn| {
+                                ln + rn
+                            }) {
+                                Some(result) => result,
+                                None => {
+                                    return Err(runtime_error!(
+                                        binary_expr.operator.line(),
+                                        "Incompatible addition types"
+                                    ));
+                                }
+                            }
                         }
-                        _ => lox_error!(
-                            "[line {}] Error: Incompatible addition types",
-                            binary_expr.operator.line()
-                        ),
                     },
-                    Token::Slash(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Number(ln / rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot divide NaNs",
-                            binary_expr.operator.line()
-                        ),
+                    // Dividing two integers still yields a float.
+                    Token::Slash(_) => match numeric_operands(&left, &right) {
+                        Some((ln, rn)) => LoxType::Number(ln / rn),
+                        None => {
+                            return Err(runtime_error!(
+                                binary_expr.operator.line(),
+                                "Operands must be numbers."
+                            ));
+                        }
                     },
-                    Token::Star(_) => match (left, right) {
-                        (LoxType::Number(ln), LoxType::Number(rn)) => LoxType::Number(ln * rn),
-                        _ => lox_error!(
-                            "[line {}] Error: Cannot multiply NaNs",
-                            binary_expr.operator.line()
-                        ),
+                    Token::Star(_) => {
+                        match arithmetic(&left, &right, LoxInteger::checked_mul, |ln, rn| ln * rn) {
+                            Some(result) => result,
+                            None => {
+                                return Err(runtime_error!(
+                                    binary_expr.operator.line(),
+                                    "Operands must be numbers."
+                                ));
+                            }
+                        }
+                    }
+                    Token::StarStar(_) => match arithmetic(
+                        &left,
+                        &right,
+                        |ln, rn| ln.checked_pow(u32::try_from(rn).ok()?),
+                        LoxNumber::powf,
+                    ) {
+                        Some(result) => result,
+                        None => {
+                            return Err(runtime_error!(
+                                binary_expr.operator.line(),
+                                "Operands must be numbers."
+                            ));
+                        }
                     },
+                    Token::Percent(_)
+                        if left.as_number().is_some() && right.as_number() == Some(0.) =>
+                    {
+                        return Err(runtime_error!(
+                            binary_expr.operator.line(),
+                            "Modulo by zero."
+                        ));
+                    }
+                    Token::Percent(_) => {
+                        match arithmetic(&left, &right, LoxInteger::checked_rem, |ln, rn| ln % rn) {
+                            Some(result) => result,
+                            None => {
+                                return Err(runtime_error!(
+                                    binary_expr.operator.line(),
+                                    "Operands must be numbers."
+                                ));
+                            }
+                        }
+                    }
+                    Token::Ampersand(_) => {
+                        let (ln, rn) = integer_operands(left, right, binary_expr.operator.line())?;
+                        LoxType::Integer(ln & rn)
+                    }
+                    Token::BitwiseOr(_) => {
+                        let (ln, rn) = integer_operands(left, right, binary_expr.operator.line())?;
+                        LoxType::Integer(ln | rn)
+                    }
+                    Token::Caret(_) => {
+                        let (ln, rn) = integer_operands(left, right, binary_expr.operator.line())?;
+                        LoxType::Integer(ln ^ rn)
+                    }
+                    Token::ShiftLeft(_) => {
+                        let line = binary_expr.operator.line();
+                        let (ln, rn) = integer_operands(left, right, line)?;
+                        LoxType::Integer(ln << shift_amount(rn, line)?)
+                    }
+                    Token::ShiftRight(_) => {
+                        let line = binary_expr.operator.line();
+                        let (ln, rn) = integer_operands(left, right, line)?;
+                        LoxType::Integer(ln >> shift_amount(rn, line)?)
+                    }
                     _ => unreachable!(),
                 };
-                return result;
+                Ok(result)
             }
             Expr::Call(call_expr) => {
-                let callee = call_expr.callee.eval(env);
+                let callee = call_expr.callee.eval(env)?;
 
-                let args = call_expr
-                    .arguments
-                    .iter()
-                    .map(|carg| carg.eval(env))
-                    .collect::<Vec<LoxType>>();
-
-                match callee {
-                    LoxType::Function(fun) => {
-                        if args.len() != fun.lock().unwrap().arity() {
-                            lox_error!(
-                                "[line {}] Error: Expected {} arguments but got {}.",
-                                call_expr.paren.line(),
-                                fun.lock().unwrap().arity(),
-                                args.len()
-                            );
-                        }
+                // The callee is evaluated first, then the arguments from left to right.
+                let mut args = Vec::with_capacity(call_expr.arguments.len());
 
-                        return fun
-                            .lock()
-                            .unwrap()
-                            .call((args, env, call_expr.paren.line()));
-                    }
-                    _ => lox_error!(
-                        "[line {}] Error: Can only call functions and classes.",
-                        call_expr.paren.line()
-                    ),
+                for carg in &call_expr.arguments {
+                    let Expr::Spread(spread_expr) = carg else {
+                        args.push(carg.eval(env)?);
+                        continue;
+                    };
+
+                    let LoxType::Array(items) = spread_expr.expression.eval(env)? else {
+                        return Err(runtime_error!(
+                            spread_expr.token.line(),
+                            "Can only spread arrays."
+                        ));
+                    };
+
+                    args.extend(items.lock().unwrap().iter().cloned());
                 }
+
+                call_function(&callee, args, env, call_expr.paren.line())
+            }
+            Expr::Function(function_expr) => {
+                let function = LoxFunction::new(&function_expr.function, env.clone());
+
+                Ok(LoxType::Function(Arc::new(function)))
             }
-            Expr::Get(get_expr) => LoxType::Unknown,
-            Expr::Grouping(grouping_expr) => {
-                return grouping_expr.expression.eval(env);
+            Expr::Get(get_expr) => {
+                let object = get_expr.object.eval(env)?;
+                get_property(object, &get_expr.name)
             }
-            Expr::Literal(literal_expr) => {
-                return match &literal_expr.value {
-                    LiteralExprType::Identifier(id) => match id {
-                        Keyword::True => LoxType::Boolean(true),
-                        Keyword::False => LoxType::Boolean(false),
-                        Keyword::Nil => LoxType::Nil,
-                        _ => LoxType::Unknown,
-                    },
-                    LiteralExprType::Number(num) => LoxType::Number(*num),
-                    LiteralExprType::String(str) => LoxType::String(str.clone()),
-                    LiteralExprType::EOF => LoxType::Unknown,
-                };
+            Expr::Grouping(grouping_expr) => grouping_expr.expression.eval(env),
+            Expr::Index(index_expr) => {
+                let object = index_expr.object.eval(env)?;
+                let index = index_expr.index.eval(env)?;
+                let line = index_expr.bracket.line();
+
+                match object {
+                    LoxType::Array(items) => {
+                        let items = items.lock().unwrap();
+                        let index = array_index(&items, index, line)?;
+
+                        Ok(items[index].clone())
+                    }
+                    LoxType::Map(entries) => {
+                        let key = map_key(index, line)?;
+                        let value = entries.lock().unwrap().get(&key).cloned();
+
+                        Ok(value.unwrap_or(LoxType::Nil))
+                    }
+                    _ => Err(runtime_error!(line, "Only arrays and maps can be indexed.")),
+                }
             }
+            Expr::IndexSet(index_set_expr) => {
+                let object = index_set_expr.object.eval(env)?;
+                let index = index_set_expr.index.eval(env)?;
+                let line = index_set_expr.bracket.line();
+
+                if !matches!(object, LoxType::Array(_) | LoxType::Map(_)) {
+                    return Err(runtime_error!(line, "Only arrays and maps can be indexed."));
+                }
+
+                let value = index_set_expr.value.eval(env)?;
+
+                match object {
+                    LoxType::Array(items) => {
+                        let mut items = items.lock().unwrap();
+                        let index = array_index(&items, index, line)?;
+                        items[index] = value.clone();
+                    }
+                    LoxType::Map(entries) => {
+                        let key = map_key(index, line)?;
+                        entries.lock().unwrap().insert(key, value.clone());
+                    }
+                    _ => unreachable!(),
+                }
+
+                Ok(value)
+            }
+            Expr::Literal(literal_expr) => Ok(match &literal_expr.value {
+                LiteralExprType::Identifier(id) => match id {
+                    Keyword::True => LoxType::Boolean(true),
+                    Keyword::False => LoxType::Boolean(false),
+                    Keyword::Nil => LoxType::Nil,
+                    _ => LoxType::Unknown,
+                },
+                LiteralExprType::Number(num) => LoxType::Number(*num),
+                LiteralExprType::Integer(num) => LoxType::Integer(*num),
+                LiteralExprType::String(str) => LoxType::String(str.clone()),
+            }),
             Expr::Logical(logical_expr) => {
-                let left = logical_expr.left.eval(env);
+                let left = logical_expr.left.eval(env)?;
 
-                match &logical_expr.operator {
-                    Token::Keyword(k) => match k.keyword {
-                        Keyword::Or => {
-                            if left.is_truthy() {
-                                return left;
-                            }
-                        }
-                        _ => {
-                            if !left.is_truthy() {
-                                return left;
-                            }
+                match logical_expr.operator {
+                    LogicalOp::Or => {
+                        if left.is_truthy() {
+                            return Ok(left);
                         }
-                    },
-                    _ => {
+                    }
+                    LogicalOp::And => {
                         if !left.is_truthy() {
-                            return left;
+                            return Ok(left);
                         }
                     }
                 }
 
-                return logical_expr.right.eval(env);
+                logical_expr.right.eval(env)
             }
-            Expr::Set(set_expr) => LoxType::Unknown,
-            Expr::Super(super_expr) => LoxType::Unknown,
-            Expr::Ternary(ternary_expr) => {
-                let condition = ternary_expr.condition.eval(env);
-                let trueish = ternary_expr.trueish.eval(env);
-                let falseish = ternary_expr.falseish.eval(env);
+            Expr::Map(map_expr) => {
+                let line = map_expr.brace.line();
+                let mut entries = HashMap::with_capacity(map_expr.pairs.len());
 
-                if condition.is_truthy() {
-                    return trueish;
+                for (key, value) in &map_expr.pairs {
+                    let key = map_key(key.eval(env)?, line)?;
+                    let value = value.eval(env)?;
+                    entries.insert(key, value);
+                }
+
+                Ok(LoxType::Map(Arc::new(Mutex::new(entries))))
+            }
+            Expr::NilCoalesce(nil_coalesce_expr) => {
+                let left = nil_coalesce_expr.left.eval(env)?;
+
+                if left != LoxType::Nil {
+                    return Ok(left);
+                }
+
+                nil_coalesce_expr.right.eval(env)
+            }
+            Expr::OptionalGet(optional_get_expr) => {
+                let object = optional_get_expr.object.eval(env)?;
+
+                if object == LoxType::Nil {
+                    return Ok(LoxType::Nil);
+                }
+
+                get_property(object, &optional_get_expr.name)
+            }
+            Expr::Set(set_expr) => {
+                let object = set_expr.object.eval(env)?;
+                let line = set_expr.name.line();
+
+                let Token::Keyword(k) = &set_expr.name else {
+                    unreachable!()
+                };
+
+                let LoxType::Instance(instance) = object else {
+                    return Err(runtime_error!(line, "Only instances have fields."));
+                };
+
+                let value = set_expr.value.eval(env)?;
+                LoxInstance::set(&instance, &k.keyword.lexeme(), value.clone());
+
+                Ok(value)
+            }
+            Expr::Spread(_) => unreachable!(),
+            Expr::Super(super_expr) => {
+                let line = super_expr.keyword.line();
+
+                let LoxType::Class(superclass) = env.get(&"super".to_string(), line)? else {
+                    unreachable!()
+                };
+                let instance = env.get(&"this".to_string(), line)?;
+
+                let Token::Keyword(k) = &super_expr.method else {
+                    unreachable!()
+                };
+                let name = k.keyword.lexeme();
+
+                let Some(method) = superclass.find_method(&name) else {
+                    return Err(runtime_error!(line, "Undefined property '{}'.", name));
+                };
+
+                Ok(LoxType::Function(Arc::new(method.bind(instance))))
+            }
+            Expr::Ternary(ternary_expr) => {
+                if ternary_expr.condition.eval(env)?.is_truthy() {
+                    ternary_expr.trueish.eval(env)
                 } else {
-                    return falseish;
+                    ternary_expr.falseish.eval(env)
                 }
             }
-            Expr::This(this_expr) => LoxType::Unknown,
+            Expr::This(this_expr) => env.get(&"this".to_string(), this_expr.keyword.line()),
             Expr::Unary(unary_expr) => {
-                let right = unary_expr.right.eval(env);
+                let right = unary_expr.right.eval(env)?;
 
                 let result = match &unary_expr.operator {
                     Token::Bang(_) => LoxType::Boolean(!right.is_truthy()),
+                    Token::Keyword(k) if k.keyword == Keyword::Typeof => {
+                        LoxType::String(LoxString::from(right.type_name()))
+                    }
                     Token::Minus(_) => match right {
                         LoxType::Number(n) => LoxType::Number(-n),
+                        LoxType::Integer(n) => n
+                            .checked_neg()
+                            .map_or(LoxType::Number(-(n as LoxNumber)), LoxType::Integer),
+                        _ => {
+                            return Err(runtime_error!(
+                                unary_expr.operator.line(),
+                                "Operand must be a number."
+                            ));
+                        }
+                    },
+                    Token::Tilde(_) => match right {
+                        LoxType::Number(n) => LoxType::Integer(!(n as LoxInteger)),
+                        LoxType::Integer(n) => LoxType::Integer(!n),
                         _ => {
-                            lox_error!("[line {}] Error: Cannot negate NaNs", unary_expr.operator)
+                            return Err(runtime_error!(
+                                unary_expr.operator.line(),
+                                "Operand must be a number."
+                            ));
                         }
                     },
                     _ => unreachable!(),
                 };
 
-                return result;
+                Ok(result)
+            }
+            Expr::Update(update_expr) => {
+                let operator = &update_expr.operator;
+                let line = operator.line();
+
+                // The target is evaluated once: read the old value, then store the stepped one.
+                let (old, new) = match update_expr.target.as_ref() {
+                    Expr::Variable(variable_expr) => {
+                        let old = env.get_at(
+                            variable_expr.depth,
+                            &variable_expr.name,
+                            variable_expr.line,
+                        )?;
+                        let new = step(&old, operator)?;
+
+                        env.assign_at(
+                            variable_expr.depth,
+                            variable_expr.name.clone(),
+                            new.clone(),
+                            variable_expr.line,
+                        )?;
+
+                        (old, new)
+                    }
+                    Expr::Get(get_expr) => {
+                        let object = get_expr.object.eval(env)?;
+
+                        let LoxType::Instance(instance) = &object else {
+                            return Err(runtime_error!(line, "Only instances have fields."));
+                        };
+
+                        let Token::Keyword(k) = &get_expr.name else {
+                            unreachable!()
+                        };
+
+                        let old = get_property(object.clone(), &get_expr.name)?;
+                        let new = step(&old, operator)?;
+                        LoxInstance::set(instance, &k.keyword.lexeme(), new.clone());
+
+                        (old, new)
+                    }
+                    Expr::Index(index_expr) => {
+                        let object = index_expr.object.eval(env)?;
+                        let index = index_expr.index.eval(env)?;
+                        let line = index_expr.bracket.line();
+
+                        match object {
+                            LoxType::Array(items) => {
+                                let mut items = items.lock().unwrap();
+                                let index = array_index(&items, index, line)?;
+
+                                let old = items[index].clone();
+                                let new = step(&old, operator)?;
+                                items[index] = new.clone();
+
+                                (old, new)
+                            }
+                            LoxType::Map(entries) => {
+                                let key = map_key(index, line)?;
+                                let mut entries = entries.lock().unwrap();
+
+                                let old = entries.get(&key).cloned().unwrap_or(LoxType::Nil);
+                                let new = step(&old, operator)?;
+                                entries.insert(key, new.clone());
+
+                                (old, new)
+                            }
+                            _ => {
+                                return Err(runtime_error!(
+                                    line,
+                                    "Only arrays and maps can be indexed."
+                                ));
+                            }
+                        }
+                    }
+                    _ => unreachable!(),
+                };
+
+                if update_expr.prefix {
+                    return Ok(new);
+                }
+
+                Ok(old)
             }
             Expr::Variable(variable_expr) => {
-                return env.get(&variable_expr.name).clone();
+                env.get_at(variable_expr.depth, &variable_expr.name, variable_expr.line)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{error, eval, global};
+
+    #[test]
+    fn evaluates_only_the_selected_ternary_branch() {
+        assert_eq!(eval("true ? 1 : nope"), "1");
+        assert_eq!(eval("false ? nope : 2"), "2");
+    }
+
+    #[test]
+    fn concatenates_strings() {
+        assert_eq!(eval("\"foo\" + \"bar\""), "foobar");
+    }
+
+    #[test]
+    fn typeof_reports_type_names() {
+        assert_eq!(eval("typeof 42"), "number");
+        assert_eq!(eval("typeof nil"), "nil");
+        assert_eq!(eval("typeof clock"), "function");
+        assert_eq!(eval("typeof \"s\""), "string");
+        assert_eq!(eval("typeof true"), "boolean");
+    }
+
+    #[test]
+    fn integer_literals_are_exact() {
+        assert_eq!(eval("9007199254740993"), "9007199254740993");
+        assert_eq!(eval("0x7FFFFFFFFFFFFFFF"), "9223372036854775807");
+        assert_eq!(eval("9223372036854775808"), "9223372036854800000");
+    }
+
+    #[test]
+    fn integer_arithmetic_stays_integral() {
+        assert_eq!(eval("2 * 3"), "6");
+        assert_eq!(eval("6 / 3"), "2");
+        assert_eq!(eval("7 / 2"), "3.5");
+        assert_eq!(eval("2 + 1.5"), "3.5");
+        assert_eq!(eval("9223372036854775807 + 1"), "9223372036854800000");
+    }
+
+    #[test]
+    fn postfix_update_returns_old_value() {
+        assert_eq!(global("var i = 1; var x = i++;", "x"), "1");
+        assert_eq!(global("var i = 1; var x = i++;", "i"), "2");
+        assert_eq!(global("var i = 1; var x = i--;", "i"), "0");
+    }
+
+    #[test]
+    fn prefix_update_returns_new_value() {
+        assert_eq!(global("var i = 1; var x = ++i;", "x"), "2");
+        assert_eq!(global("var i = 1; var x = --i;", "x"), "0");
+    }
+
+    #[test]
+    fn update_evaluates_property_target_once() {
+        let source = "
+            class P {}
+            var p = P();
+            p.n = 5;
+            var calls = 0;
+            fun get() { calls = calls + 1; return p; }
+            var old = get().n++;
+            var n = p.n;
+        ";
+
+        assert_eq!(global(source, "old"), "5");
+        assert_eq!(global(source, "n"), "6");
+        assert_eq!(global(source, "calls"), "1");
+    }
+
+    #[test]
+    fn update_evaluates_index_target_once() {
+        let source = "
+            var a = [1, 2];
+            var calls = 0;
+            fun index() { calls = calls + 1; return 1; }
+            var old = a[index()]--;
+            var value = a[1];
+        ";
+
+        assert_eq!(global(source, "old"), "2");
+        assert_eq!(global(source, "value"), "1");
+        assert_eq!(global(source, "calls"), "1");
+    }
+
+    #[test]
+    fn indexes_and_assigns_arrays() {
+        assert_eq!(eval("[1, 2, 3][1]"), "2");
+        assert_eq!(global("var a = [1, 2, 3]; a[0] = 9;", "a"), "[9, 2, 3]");
+        assert_eq!(
+            error("[1][1];"),
+            "[line 1] Error: Array index 1 is out of bounds for an array of length 1."
+        );
+    }
+
+    #[test]
+    fn indexes_and_assigns_maps() {
+        let source = "
+            var m = {\"a\": 1, b: 2};
+            m[\"c\"] = 3;
+            var a = m[\"a\"];
+            var missing = m[\"zzz\"];
+        ";
+
+        assert_eq!(global(source, "a"), "1");
+        assert_eq!(global(source, "missing"), "nil");
+        assert_eq!(global(source, "m"), "{a: 1, b: 2, c: 3}");
+    }
+
+    #[test]
+    fn evaluates_arguments_left_to_right() {
+        let source = "
+            var log = \"\";
+            fun a() { log = log + \"a\"; return 1; }
+            fun b() { log = log + \"b\"; return 2; }
+            fun f(x, y) { log = log + \"f\"; }
+            f(a(), b());
+        ";
+
+        assert_eq!(global(source, "log"), "abf");
+    }
+
+    #[test]
+    fn runs_compound_assignment() {
+        let source = "
+            var a = 10; a += 5;
+            var b = 10; b -= 3;
+            var c = 10; c *= 2;
+            var d = 10; d /= 4;
+            var e = 10; e %= 3;
+            var f = 3; f **= 2;
+            var s = \"a\"; s += \"b\";
+        ";
+
+        assert_eq!(global(source, "a"), "15");
+        assert_eq!(global(source, "b"), "7");
+        assert_eq!(global(source, "c"), "20");
+        assert_eq!(global(source, "d"), "2.5");
+        assert_eq!(global(source, "e"), "1");
+        assert_eq!(global(source, "f"), "9");
+        assert_eq!(global(source, "s"), "ab");
+    }
+
+    #[test]
+    fn computes_modulo() {
+        assert_eq!(eval("7 % 3"), "1");
+        assert_eq!(eval("7.5 % 2"), "1.5");
+        assert_eq!(eval("-7 % 3"), "-1");
+        assert_eq!(eval("7 % -3"), "1");
+    }
+
+    #[test]
+    fn rejects_modulo_by_zero() {
+        assert_eq!(error("5 % 0;"), "[line 1] Error: Modulo by zero.");
+        assert_eq!(error("5.5 % 0.0;"), "[line 1] Error: Modulo by zero.");
+        assert_eq!(
+            error("\"a\" % 2;"),
+            "[line 1] Error: Operands must be numbers."
+        );
+    }
+
+    #[test]
+    fn runs_function_expressions() {
+        let source = "
+            var add_one = fun (x) { return x + 1; };
+            var assigned = add_one(1);
+            var immediate = (fun (x) { return x * 2; })(5);
+            fun apply(f, x) { return f(x); }
+            var passed = apply(fun (x) { return x - 1; }, 10);
+            fun adder(n) { return fun (x) { return x + n; }; }
+            var closed = adder(3)(4);
+        ";
+
+        assert_eq!(global(source, "assigned"), "2");
+        assert_eq!(global(source, "immediate"), "10");
+        assert_eq!(global(source, "passed"), "9");
+        assert_eq!(global(source, "closed"), "7");
+    }
+
+    #[test]
+    fn guards_nil_with_optional_get_and_coalescing() {
+        let source = "
+            class Node { init(next) { this.next = next; this.value = 1; } }
+            var chain = Node(Node(nil));
+            var deep = chain?.next?.value;
+            var missing = chain?.next?.next?.value;
+            var fallback = missing ?? \"default\";
+            var kept = 0 ?? \"default\";
+            var calls = 0;
+            fun side() { calls = calls + 1; return 2; }
+            var skipped = 1 ?? side();
+        ";
+
+        assert_eq!(global(source, "deep"), "1");
+        assert_eq!(global(source, "missing"), "nil");
+        assert_eq!(global(source, "fallback"), "default");
+        assert_eq!(global(source, "kept"), "0");
+        assert_eq!(global(source, "calls"), "0");
+    }
+
+    #[test]
+    fn logical_assignment_short_circuits() {
+        let source = "
+            var calls = 0;
+            fun side() { calls = calls + 1; return \"new\"; }
+            var truthy = 1; truthy &&= side();
+            var falsy = false; falsy &&= side();
+            var set = nil; set ||= side();
+            var kept = \"old\"; kept ||= side();
+            var filled = nil; filled ??= side();
+            var present = false; present ??= side();
+        ";
+
+        assert_eq!(global(source, "truthy"), "new");
+        assert_eq!(global(source, "falsy"), "false");
+        assert_eq!(global(source, "set"), "new");
+        assert_eq!(global(source, "kept"), "old");
+        assert_eq!(global(source, "filled"), "new");
+        assert_eq!(global(source, "present"), "false");
+        assert_eq!(global(source, "calls"), "3");
+    }
+
+    #[test]
+    fn compares_strings_lexicographically() {
+        assert_eq!(eval("\"abc\" < \"abd\""), "true");
+        assert_eq!(eval("\"z\" > \"a\""), "true");
+        assert_eq!(eval("\"abc\" <= \"abc\""), "true");
+        assert_eq!(eval("\"abc\" >= \"abd\""), "false");
+        assert_eq!(
+            error("\"a\" < 1;"),
+            "[line 1] Error: Operands must be two numbers or two strings."
+        );
+    }
+}
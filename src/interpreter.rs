@@ -1,56 +1,270 @@
 use std::{
     collections::HashMap,
+    io::{self, BufRead, Write},
+    sync::{Arc, Mutex},
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
+    LoxError, Result,
     environment::Environment,
-    lox_type::{LoxNumber, LoxType},
-    statement::Statement,
+    lox_map, lox_math, lox_native_fn,
+    lox_type::{LoxCallableArgs, LoxInteger, LoxNumber, LoxString, LoxType},
+    parser::Parser,
+    runtime_error, scan,
+    statement::{Statement, StatementSignal},
 };
 
-macro_rules! lox_native_fn {
-    ($arity:expr, $func:expr) => {{
-        use crate::lox_type::LoxNativeFunction;
-        use crate::lox_type::LoxType;
-        use std::sync::Arc;
-        use std::sync::Mutex;
-
-        LoxType::Function(Arc::new(Mutex::new(LoxNativeFunction {
-            arity: $arity,
-            body: Arc::new($func),
-        })))
-    }};
+type InputReader = Arc<Mutex<dyn BufRead + Send>>;
+
+/// Builds the `input([prompt])` native, reading from `reader` or from stdin when there is none.
+fn input_fn(reader: Option<InputReader>) -> LoxType {
+    lox_native_fn!(0..=1, move |(args, _, line): LoxCallableArgs| {
+        if let Some(prompt) = args.first() {
+            print!("{}", prompt);
+            let _ = io::stdout().flush();
+        }
+
+        let mut input = String::new();
+
+        let read = match &reader {
+            Some(reader) => reader.lock().unwrap().read_line(&mut input),
+            None => io::stdin().read_line(&mut input),
+        };
+
+        if let Err(err) = read {
+            return Err(runtime_error!(line, "Could not read input: {}.", err));
+        }
+
+        let input = input.strip_suffix('\n').unwrap_or(&input);
+        let input = input.strip_suffix('\r').unwrap_or(input);
+
+        Ok(LoxType::String(LoxString::from(input)))
+    })
 }
 
 pub struct Interpreter {
     env: Environment,
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interpreter {
+    /// Creates an interpreter with an empty global scope holding only the native functions.
     pub fn new() -> Self {
         let mut values = HashMap::new();
 
-        let clock_fn = |_| {
+        let clock_fn = |_: LoxCallableArgs| {
             let now = SystemTime::now();
 
             let duration_since_epoch = now
                 .duration_since(UNIX_EPOCH)
                 .expect("Time went backwards lol.");
 
-            return LoxType::Number(duration_since_epoch.as_millis() as LoxNumber);
+            Ok(LoxType::Number(
+                duration_since_epoch.as_millis() as LoxNumber
+            ))
+        };
+
+        let len_fn = |(args, _, line): LoxCallableArgs| match &args[0] {
+            LoxType::String(s) => Ok(LoxType::Integer(s.chars().count() as LoxInteger)),
+            LoxType::Array(a) => Ok(LoxType::Integer(a.lock().unwrap().len() as LoxInteger)),
+            _ => Err(runtime_error!(
+                line,
+                "len() argument must be a string or array."
+            )),
+        };
+
+        let str_fn = |(args, _, _): LoxCallableArgs| {
+            Ok(LoxType::String(LoxString::from(args[0].to_string())))
+        };
+
+        let num_fn = |(args, _, _): LoxCallableArgs| {
+            let value = match &args[0] {
+                LoxType::Number(n) => LoxType::Number(*n),
+                LoxType::Integer(n) => LoxType::Integer(*n),
+                LoxType::String(s) => match s.parse::<LoxInteger>() {
+                    Ok(n) => LoxType::Integer(n),
+                    Err(_) => s.parse::<LoxNumber>().map_or(LoxType::Nil, LoxType::Number),
+                },
+                _ => LoxType::Nil,
+            };
+
+            Ok(value)
         };
 
         values.insert("clock".to_string(), lox_native_fn!(0, clock_fn));
+        values.insert("len".to_string(), lox_native_fn!(1, len_fn));
+        values.insert("str".to_string(), lox_native_fn!(1, str_fn));
+        values.insert("num".to_string(), lox_native_fn!(1, num_fn));
+        values.insert("input".to_string(), input_fn(None));
+        values.insert("Math".to_string(), lox_math::module());
+        values.extend(lox_map::natives());
+        values.insert("Inf".to_string(), LoxType::Number(LoxNumber::INFINITY));
+        values.insert("NaN".to_string(), LoxType::Number(LoxNumber::NAN));
+        values.insert(
+            "__version__".to_string(),
+            LoxType::String(LoxString::from(env!("CARGO_PKG_VERSION"))),
+        );
+        values.insert(
+            "__interpreter__".to_string(),
+            LoxType::String(LoxString::from("lox-rust")),
+        );
 
         let env = Environment::new(None, values);
 
         Self { env }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Statement>) {
-        statements.iter().for_each(|s| {
-            let _ = s.eval(&mut self.env);
-        });
+    pub fn get_global(&self, name: &str) -> Option<LoxType> {
+        return self
+            .env
+            .values
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|(value, _)| value.clone());
+    }
+
+    pub fn set_global(&mut self, name: &str, value: LoxType) {
+        self.env.define(name.to_string(), value);
+    }
+
+    /// Makes `input()` read lines from `reader` instead of stdin.
+    pub fn set_input(&mut self, reader: impl BufRead + Send + 'static) {
+        self.set_global("input", input_fn(Some(Arc::new(Mutex::new(reader)))));
+    }
+
+    pub fn eval_expr(&mut self, source: &str) -> Result<LoxType> {
+        let tokens = scan(source)?;
+
+        let mut parser = Parser::new(tokens);
+        let expr = parser.parse_expression()?;
+
+        Ok(expr.eval(&mut self.env)?)
+    }
+
+    /// Scans, parses and runs `source` against this interpreter's global scope.
+    pub fn run_source(&mut self, source: &str) -> Result<Vec<Option<LoxType>>> {
+        let tokens = scan(source)?;
+
+        let mut parser = Parser::new(tokens);
+        let statements = parser.parse()?;
+
+        Ok(self.interpret(&statements)?)
+    }
+
+    /// Runs `statements`, as returned by [`Parser::parse`], and returns one entry per statement,
+    /// holding the value of top-level expression statements.
+    pub fn interpret(
+        &mut self,
+        statements: &[Statement],
+    ) -> std::result::Result<Vec<Option<LoxType>>, LoxError> {
+        let mut values = Vec::with_capacity(statements.len());
+
+        for stmt in statements.iter() {
+            if let Statement::Expression(expr) = stmt {
+                values.push(Some(expr.eval(&mut self.env)?));
+                continue;
+            }
+
+            match stmt.eval(&mut self.env) {
+                Ok(()) => values.push(None),
+                Err(StatementSignal::Error(err)) => return Err(err),
+                Err(StatementSignal::Return(_, line)) => {
+                    return Err(runtime_error!(line, "Can't return from top-level code."));
+                }
+                Err(StatementSignal::Break(line) | StatementSignal::BreakLabel(_, line)) => {
+                    return Err(runtime_error!(
+                        line,
+                        "Cannot use 'break' outside of a loop or switch."
+                    ));
+                }
+                Err(StatementSignal::Continue(line) | StatementSignal::ContinueLabel(_, line)) => {
+                    return Err(runtime_error!(
+                        line,
+                        "Cannot use 'continue' outside of a loop."
+                    ));
+                }
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{error, eval};
+
+    #[test]
+    fn returns_values_of_top_level_expressions() {
+        let values = Interpreter::new()
+            .run_source("1 + 1; var x = 2; x;")
+            .unwrap();
+        let values = values
+            .into_iter()
+            .map(|value| value.map(|value| value.to_string()))
+            .collect::<Vec<Option<String>>>();
+
+        assert_eq!(
+            values,
+            vec![Some("2".to_string()), None, Some("2".to_string())]
+        );
+    }
+
+    #[test]
+    fn reads_input_from_the_injected_reader() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_input(io::Cursor::new("first\r\nsecond\n"));
+        interpreter
+            .run_source("var a = input(); var b = input(); var c = input();")
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        let value = |name: &str| interpreter.get_global(name).unwrap().to_string();
+
+        assert_eq!(value("a"), "first");
+        assert_eq!(value("b"), "second");
+        assert_eq!(value("c"), "");
+    }
+
+    #[test]
+    fn len_counts_characters_and_elements() {
+        assert_eq!(eval("len(\"\")"), "0");
+        assert_eq!(eval("len(\"abc\")"), "3");
+        assert_eq!(eval("len(\"café\")"), "4");
+        assert_eq!(eval("len([1, 2])"), "2");
+        assert_eq!(
+            error("len(1);"),
+            "[line 1] Error: len() argument must be a string or array."
+        );
+    }
+
+    #[test]
+    fn converts_between_strings_and_numbers() {
+        assert_eq!(eval("str(3.0)"), "3");
+        assert_eq!(eval("str(3.14)"), "3.14");
+        assert_eq!(eval("str(true) + str(nil)"), "truenil");
+        assert_eq!(eval("num(\"42\") + 1"), "43");
+        assert_eq!(eval("num(\"2.5\")"), "2.5");
+        assert_eq!(eval("num(\"bad\")"), "nil");
+    }
+
+    #[test]
+    fn reruns_a_parsed_program() {
+        let tokens = scan("{ var step = 1; count = count + step; }").unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("count", LoxType::Integer(0));
+        interpreter.interpret(&statements).unwrap();
+        interpreter.interpret(&statements).unwrap();
+
+        assert_eq!(interpreter.get_global("count").unwrap().to_string(), "2");
     }
 }
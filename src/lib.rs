@@ -0,0 +1,166 @@
+mod environment;
+mod expression;
+mod interpreter;
+mod lox_array;
+mod lox_map;
+mod lox_math;
+#[cfg(feature = "serde")]
+mod lox_serde;
+mod lox_string;
+mod lox_type;
+mod parser;
+mod resolver;
+mod scanner;
+mod statement;
+#[cfg(test)]
+mod testing;
+mod token;
+mod token_cursor;
+
+pub use crate::interpreter::Interpreter;
+pub use crate::lox_type::LoxType;
+pub use crate::parser::Parser;
+
+use crate::scanner::Scanner;
+use crate::token::Token;
+
+#[macro_export]
+macro_rules! lox_panic {
+    ($fmt:expr $(, $($arg:tt)+ )? ) => {{
+        eprintln!($fmt $(, $($arg)+ )?);
+        std::process::exit(1);
+    }};
+}
+
+#[macro_export]
+macro_rules! runtime_error {
+    ($line:expr, $($arg:tt)+) => {
+        $crate::LoxError::Runtime {
+            line: $line,
+            message: format!($($arg)+),
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! parse_error {
+    ($line:expr, $($arg:tt)+) => {
+        $crate::LoxError::Compile($crate::CompileError::new(
+            $line,
+            None,
+            String::new(),
+            format!($($arg)+),
+        ))
+    };
+}
+
+#[macro_export]
+macro_rules! lox_native_fn {
+    ($min_arity:literal ..= $arity:literal, $func:expr) => {{
+        $crate::lox_type::LoxType::Function(std::sync::Arc::new(
+            $crate::lox_type::LoxNativeFunction {
+                min_arity: $min_arity,
+                arity: $arity,
+                body: std::sync::Arc::new($func),
+            },
+        ))
+    }};
+
+    ($arity:expr, $func:expr) => {{
+        $crate::lox_type::LoxType::Function(std::sync::Arc::new(
+            $crate::lox_type::LoxNativeFunction {
+                min_arity: $arity,
+                arity: $arity,
+                body: std::sync::Arc::new($func),
+            },
+        ))
+    }};
+}
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug)]
+pub struct CompileErrors(Vec<CompileError>);
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    line: usize,
+    column: Option<usize>,
+    kind: String,
+    message: String,
+}
+
+impl CompileError {
+    fn new(line: usize, column: Option<usize>, kind: String, message: String) -> Self {
+        Self {
+            line,
+            column,
+            kind,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.column {
+            Some(column) => write!(
+                f,
+                "[line {}, col {}] Error{}: {}",
+                self.line, column, self.kind, self.message
+            ),
+            None => write!(
+                f,
+                "[line {}] Error{}: {}",
+                self.line, self.kind, self.message
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LoxError {
+    Compile(CompileError),
+    Runtime { line: usize, message: String },
+    Thrown { line: usize, value: LoxType },
+}
+
+impl std::fmt::Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxError::Compile(err) => write!(f, "{}", err),
+            LoxError::Runtime { line, message } => write!(f, "[line {}] Error: {}", line, message),
+            LoxError::Thrown { line, value } => write!(f, "[line {}] Error: {}", line, value),
+        }
+    }
+}
+
+impl std::fmt::Display for CompileErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut output = String::new();
+
+        self.0.iter().for_each(|err| {
+            output.push_str(format!("{}\n", err).as_str());
+        });
+
+        write!(f, "{}", output)
+    }
+}
+
+impl std::error::Error for CompileError {}
+impl std::error::Error for CompileErrors {}
+impl std::error::Error for LoxError {}
+
+pub fn error(line: usize, col: usize, message: &str) -> CompileError {
+    CompileError::new(line, Some(col), "".to_string(), message.to_string())
+}
+
+pub fn scan(source: &str) -> Result<Vec<Token>> {
+    let (tokens, errs) = Scanner::new(source).finish();
+
+    if !errs.is_empty() {
+        return Err(Box::new(CompileErrors(errs)));
+    }
+
+    Ok(tokens)
+}
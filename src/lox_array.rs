@@ -0,0 +1,248 @@
+use std::{
+    cmp::Ordering,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    LoxError, lox_native_fn,
+    lox_type::{
+        LoxArray, LoxCallableArgs, LoxInteger, LoxString, LoxType, call_function, index_arg,
+        string_arg,
+    },
+    runtime_error,
+};
+
+fn compare(left: &LoxType, right: &LoxType) -> Option<Ordering> {
+    match (left, right) {
+        (LoxType::Integer(ln), LoxType::Integer(rn)) => Some(ln.cmp(rn)),
+        (LoxType::Number(_) | LoxType::Integer(_), LoxType::Number(_) | LoxType::Integer(_)) => {
+            Some(
+                left.as_number()
+                    .partial_cmp(&right.as_number())
+                    .unwrap_or(Ordering::Equal),
+            )
+        }
+        (LoxType::String(ls), LoxType::String(rs)) => Some(ls.cmp(rs)),
+        _ => None,
+    }
+}
+
+pub fn get(array: &LoxArray, name: &str, line: usize) -> Result<LoxType, LoxError> {
+    let items = array.clone();
+
+    let property = match name {
+        "length" => LoxType::Integer(items.lock().unwrap().len() as LoxInteger),
+        "push" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            items.lock().unwrap().push(args[0].clone());
+            Ok(LoxType::Nil)
+        }),
+        "pop" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            Ok(items.lock().unwrap().pop().unwrap_or(LoxType::Nil))
+        }),
+        "shift" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            let mut items = items.lock().unwrap();
+
+            if items.is_empty() {
+                Ok(LoxType::Nil)
+            } else {
+                Ok(items.remove(0))
+            }
+        }),
+        "unshift" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            items.lock().unwrap().insert(0, args[0].clone());
+            Ok(LoxType::Nil)
+        }),
+        "slice" => lox_native_fn!(2, move |(args, _, _): LoxCallableArgs| {
+            let start = index_arg(&args, 0, "slice", line)?;
+            let end = index_arg(&args, 1, "slice", line)?;
+            let items = items.lock().unwrap();
+
+            if start > end || end > items.len() {
+                return Err(runtime_error!(
+                    line,
+                    "Slice range {}..{} is out of bounds for an array of length {}.",
+                    start,
+                    end,
+                    items.len()
+                ));
+            }
+
+            Ok(LoxType::Array(Arc::new(Mutex::new(
+                items[start..end].to_vec(),
+            ))))
+        }),
+        "concat" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let other = match &args[0] {
+                LoxType::Array(other) => other.lock().unwrap().clone(),
+                _ => {
+                    return Err(runtime_error!(
+                        line,
+                        "Argument 1 of 'concat' must be an array."
+                    ));
+                }
+            };
+
+            let mut result = items.lock().unwrap().clone();
+            result.extend(other);
+
+            Ok(LoxType::Array(Arc::new(Mutex::new(result))))
+        }),
+        "join" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let separator = string_arg(&args, 0, "join", line)?;
+
+            let joined = items
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<String>>()
+                .join(&separator);
+
+            Ok(LoxType::String(LoxString::from(joined)))
+        }),
+        "reverse" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            items.lock().unwrap().reverse();
+            Ok(LoxType::Array(items.clone()))
+        }),
+        "sort" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            let mut sortable = true;
+
+            items.lock().unwrap().sort_by(|left, right| {
+                compare(left, right).unwrap_or_else(|| {
+                    sortable = false;
+                    Ordering::Equal
+                })
+            });
+
+            if !sortable {
+                return Err(runtime_error!(
+                    line,
+                    "Can only sort arrays of numbers or arrays of strings."
+                ));
+            }
+
+            Ok(LoxType::Array(items.clone()))
+        }),
+        "map" => lox_native_fn!(1, move |(args, env, _): LoxCallableArgs| {
+            let snapshot = items.lock().unwrap().clone();
+
+            let mut mapped = Vec::with_capacity(snapshot.len());
+
+            for item in snapshot {
+                mapped.push(call_function(&args[0], vec![item], env, line)?);
+            }
+
+            Ok(LoxType::Array(Arc::new(Mutex::new(mapped))))
+        }),
+        "filter" => lox_native_fn!(1, move |(args, env, _): LoxCallableArgs| {
+            let snapshot = items.lock().unwrap().clone();
+
+            let mut filtered = Vec::new();
+
+            for item in snapshot {
+                if call_function(&args[0], vec![item.clone()], env, line)?.is_truthy() {
+                    filtered.push(item);
+                }
+            }
+
+            Ok(LoxType::Array(Arc::new(Mutex::new(filtered))))
+        }),
+        "reduce" => lox_native_fn!(2, move |(args, env, _): LoxCallableArgs| {
+            let snapshot = items.lock().unwrap().clone();
+
+            let mut acc = args[1].clone();
+
+            for item in snapshot {
+                acc = call_function(&args[0], vec![acc, item], env, line)?;
+            }
+
+            Ok(acc)
+        }),
+        "forEach" => lox_native_fn!(1, move |(args, env, _): LoxCallableArgs| {
+            let snapshot = items.lock().unwrap().clone();
+
+            for item in snapshot {
+                call_function(&args[0], vec![item], env, line)?;
+            }
+
+            Ok(LoxType::Nil)
+        }),
+        "find" => lox_native_fn!(1, move |(args, env, _): LoxCallableArgs| {
+            let snapshot = items.lock().unwrap().clone();
+
+            for item in snapshot {
+                if call_function(&args[0], vec![item.clone()], env, line)?.is_truthy() {
+                    return Ok(item);
+                }
+            }
+
+            Ok(LoxType::Nil)
+        }),
+        "indexOf" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let index = items
+                .lock()
+                .unwrap()
+                .iter()
+                .position(|item| *item == args[0]);
+
+            Ok(LoxType::Integer(index.map_or(-1, |i| i as LoxInteger)))
+        }),
+        _ => {
+            return Err(runtime_error!(
+                line,
+                "Undefined property '{}' on array.",
+                name
+            ));
+        }
+    };
+
+    Ok(property)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{error, global};
+
+    #[test]
+    fn runs_array_methods() {
+        let source = "
+            var a = [3, 1];
+            a.push(2);
+            var popped = a.pop();
+            a.unshift(5);
+            var shifted = a.shift();
+            var joined = a.concat([4]).sort().join(\",\");
+            var sliced = [1, 2, 3, 4].slice(1, 3);
+            var reversed = [1, 2].reverse();
+            var length = a.length;
+        ";
+
+        assert_eq!(global(source, "popped"), "2");
+        assert_eq!(global(source, "shifted"), "5");
+        assert_eq!(global(source, "joined"), "1,3,4");
+        assert_eq!(global(source, "sliced"), "[2, 3]");
+        assert_eq!(global(source, "reversed"), "[2, 1]");
+        assert_eq!(global(source, "length"), "2");
+    }
+
+    #[test]
+    fn array_callbacks_propagate_errors() {
+        assert_eq!(
+            error("[1, 2].map(fun (x) { return nope; });"),
+            "[line 1] Error: Undefined variable 'nope'."
+        );
+        assert_eq!(
+            error("[1, 2].reduce(fun (acc, x) { return acc + nil; }, 0);"),
+            error("0 + nil;")
+        );
+    }
+
+    #[test]
+    fn sorts_only_numbers_or_strings() {
+        assert_eq!(global("var a = [\"b\", \"a\"].sort();", "a"), "[a, b]");
+        assert_eq!(
+            error("[1, \"a\"].sort();"),
+            "[line 1] Error: Can only sort arrays of numbers or arrays of strings."
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    LoxError, lox_native_fn,
+    lox_type::{LoxCallableArgs, LoxFunctionArgs, LoxMap, LoxString, LoxType, string_arg},
+    runtime_error,
+};
+
+fn map_arg(args: &LoxFunctionArgs, method: &str, line: usize) -> Result<LoxMap, LoxError> {
+    match &args[0] {
+        LoxType::Map(entries) => Ok(entries.clone()),
+        _ => Err(runtime_error!(
+            line,
+            "Argument 1 of '{}' must be a map.",
+            method
+        )),
+    }
+}
+
+/// Builds the `map_*` native functions that operate on map values.
+pub fn natives() -> HashMap<String, LoxType> {
+    let mut values = HashMap::new();
+
+    values.insert(
+        "map_get".to_string(),
+        lox_native_fn!(2, |(args, _, line): LoxCallableArgs| {
+            let entries = map_arg(&args, "map_get", line)?;
+            let key = string_arg(&args, 1, "map_get", line)?;
+            let value = entries.lock().unwrap().get(&*key).cloned();
+
+            Ok(value.unwrap_or(LoxType::Nil))
+        }),
+    );
+    values.insert(
+        "map_set".to_string(),
+        lox_native_fn!(3, |(args, _, line): LoxCallableArgs| {
+            let entries = map_arg(&args, "map_set", line)?;
+            let key = string_arg(&args, 1, "map_set", line)?;
+            entries
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), args[2].clone());
+
+            Ok(args[2].clone())
+        }),
+    );
+    values.insert(
+        "map_has".to_string(),
+        lox_native_fn!(2, |(args, _, line): LoxCallableArgs| {
+            let entries = map_arg(&args, "map_has", line)?;
+            let key = string_arg(&args, 1, "map_has", line)?;
+            let has = entries.lock().unwrap().contains_key(&*key);
+
+            Ok(LoxType::Boolean(has))
+        }),
+    );
+    values.insert(
+        "map_del".to_string(),
+        lox_native_fn!(2, |(args, _, line): LoxCallableArgs| {
+            let entries = map_arg(&args, "map_del", line)?;
+            let key = string_arg(&args, 1, "map_del", line)?;
+            let removed = entries.lock().unwrap().remove(&*key);
+
+            Ok(removed.unwrap_or(LoxType::Nil))
+        }),
+    );
+    values.insert(
+        "map_keys".to_string(),
+        lox_native_fn!(1, |(args, _, line): LoxCallableArgs| {
+            let entries = map_arg(&args, "map_keys", line)?;
+
+            let mut keys = entries
+                .lock()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<String>>();
+            keys.sort();
+
+            let keys = keys
+                .into_iter()
+                .map(|key| LoxType::String(LoxString::from(key)))
+                .collect::<Vec<LoxType>>();
+
+            Ok(LoxType::Array(Arc::new(Mutex::new(keys))))
+        }),
+    );
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::global;
+
+    #[test]
+    fn runs_map_natives() {
+        let source = "
+            var m = {};
+            map_set(m, \"x\", 1);
+            map_set(m, \"y\", 2);
+            var got = map_get(m, \"x\");
+            var had = map_has(m, \"y\");
+            var removed = map_del(m, \"y\");
+            var has = map_has(m, \"y\");
+            var keys = map_keys(m);
+        ";
+
+        assert_eq!(global(source, "got"), "1");
+        assert_eq!(global(source, "had"), "true");
+        assert_eq!(global(source, "removed"), "2");
+        assert_eq!(global(source, "has"), "false");
+        assert_eq!(global(source, "keys"), "[x]");
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    lox_native_fn,
+    lox_type::{LoxCallableArgs, LoxClass, LoxInstance, LoxNumber, LoxType, number_arg},
+};
+
+fn unary(name: &'static str, f: fn(LoxNumber) -> LoxNumber) -> LoxType {
+    lox_native_fn!(1, move |(args, _, line): LoxCallableArgs| {
+        Ok(LoxType::Number(f(number_arg(&args, 0, name, line)?)))
+    })
+}
+
+fn binary(name: &'static str, f: fn(LoxNumber, LoxNumber) -> LoxNumber) -> LoxType {
+    lox_native_fn!(2, move |(args, _, line): LoxCallableArgs| {
+        let left = number_arg(&args, 0, name, line)?;
+        let right = number_arg(&args, 1, name, line)?;
+        Ok(LoxType::Number(f(left, right)))
+    })
+}
+
+/// Builds the `Math` global, an instance whose fields hold the math constants and functions.
+pub fn module() -> LoxType {
+    let mut fields = HashMap::new();
+
+    fields.insert("PI".to_string(), LoxType::Number(std::f64::consts::PI));
+    fields.insert("E".to_string(), LoxType::Number(std::f64::consts::E));
+
+    fields.insert("abs".to_string(), unary("abs", LoxNumber::abs));
+    fields.insert("floor".to_string(), unary("floor", LoxNumber::floor));
+    fields.insert("ceil".to_string(), unary("ceil", LoxNumber::ceil));
+    fields.insert("round".to_string(), unary("round", LoxNumber::round));
+    fields.insert("sqrt".to_string(), unary("sqrt", LoxNumber::sqrt));
+    fields.insert("log".to_string(), unary("log", LoxNumber::ln));
+    fields.insert("exp".to_string(), unary("exp", LoxNumber::exp));
+    fields.insert("sin".to_string(), unary("sin", LoxNumber::sin));
+    fields.insert("cos".to_string(), unary("cos", LoxNumber::cos));
+    fields.insert("tan".to_string(), unary("tan", LoxNumber::tan));
+
+    fields.insert("pow".to_string(), binary("pow", LoxNumber::powf));
+    fields.insert("max".to_string(), binary("max", LoxNumber::max));
+    fields.insert("min".to_string(), binary("min", LoxNumber::min));
+
+    let class = Arc::new(LoxClass {
+        name: "Math".to_string(),
+        superclass: None,
+        methods: HashMap::new(),
+    });
+
+    LoxType::Instance(Arc::new(Mutex::new(LoxInstance { class, fields })))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{error, eval};
+
+    #[test]
+    fn runs_math_functions() {
+        assert_eq!(eval("Math.sqrt(9)"), "3");
+        assert_eq!(eval("Math.abs(-5)"), "5");
+        assert_eq!(eval("Math.max(2, 7) - Math.min(2, 7)"), "5");
+        assert_eq!(eval("Math.pow(2, 10)"), "1024");
+        assert_eq!(eval("Math.floor(Math.PI)"), "3");
+    }
+
+    #[test]
+    fn math_domain_errors_yield_nan() {
+        assert_eq!(eval("Math.sqrt(-1)"), "NaN");
+        assert_eq!(eval("Math.sqrt(-1) == Math.sqrt(-1)"), "false");
+    }
+
+    #[test]
+    fn rejects_non_number_arguments() {
+        assert_eq!(
+            error("Math.sqrt(\"x\");"),
+            "[line 1] Error: Argument 1 of 'sqrt' must be a number."
+        );
+    }
+}
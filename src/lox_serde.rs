@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+};
+
+use crate::lox_type::{LoxInteger, LoxNumber, LoxString, LoxType};
+
+impl Serialize for LoxType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            LoxType::Nil | LoxType::Unknown => serializer.serialize_unit(),
+            LoxType::Boolean(b) => serializer.serialize_bool(*b),
+            LoxType::Number(n) => serializer.serialize_f64(*n),
+            LoxType::Integer(n) => serializer.serialize_i64(*n),
+            LoxType::String(s) => serializer.serialize_str(s),
+            LoxType::Array(items) => {
+                let items = items.lock().unwrap();
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+
+                for item in items.iter() {
+                    seq.serialize_element(item)?;
+                }
+
+                seq.end()
+            }
+            LoxType::Map(entries) => {
+                let entries = entries.lock().unwrap();
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+
+                for (key, value) in entries.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+
+                map.end()
+            }
+            LoxType::Function(_) | LoxType::Class(_) | LoxType::Instance(_) => {
+                serializer.serialize_str(&self.to_string())
+            }
+        }
+    }
+}
+
+struct LoxTypeVisitor;
+
+impl<'de> Visitor<'de> for LoxTypeVisitor {
+    type Value = LoxType;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "nil, a boolean, a number, a string, an array or a map")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<LoxType, E> {
+        Ok(LoxType::Nil)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<LoxType, E> {
+        Ok(LoxType::Nil)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<LoxType, E> {
+        Ok(LoxType::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<LoxType, E> {
+        Ok(LoxType::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<LoxType, E> {
+        Ok(LoxInteger::try_from(v).map_or(LoxType::Number(v as LoxNumber), LoxType::Integer))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<LoxType, E> {
+        Ok(LoxType::Number(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<LoxType, E> {
+        Ok(LoxType::String(LoxString::from(v)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<LoxType, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(LoxType::Array(Arc::new(Mutex::new(items))))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<LoxType, A::Error> {
+        let mut entries = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+
+        while let Some((key, value)) = map.next_entry::<String, LoxType>()? {
+            entries.insert(key, value);
+        }
+
+        Ok(LoxType::Map(Arc::new(Mutex::new(entries))))
+    }
+}
+
+impl<'de> Deserialize<'de> for LoxType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LoxType, D::Error> {
+        deserializer.deserialize_any(LoxTypeVisitor)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::Interpreter;
+
+    fn to_json(value: &LoxType) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    fn global(source: &str, name: &str) -> LoxType {
+        let mut interpreter = Interpreter::new();
+        interpreter.run_source(source).unwrap();
+
+        interpreter.get_global(name).unwrap()
+    }
+
+    #[test]
+    fn serializes_scalars() {
+        assert_eq!(to_json(&LoxType::Nil), Value::Null);
+        assert_eq!(to_json(&LoxType::Boolean(true)), json!(true));
+        assert_eq!(to_json(&LoxType::Integer(-3)), json!(-3));
+        assert_eq!(to_json(&LoxType::Number(1.5)), json!(1.5));
+        assert_eq!(
+            to_json(&LoxType::String(LoxString::from("hi"))),
+            json!("hi")
+        );
+    }
+
+    #[test]
+    fn serializes_arrays_and_maps() {
+        let value = global("var value = {\"list\": [1, nil, \"two\"]};", "value");
+
+        assert_eq!(to_json(&value), json!({ "list": [1, null, "two"] }));
+    }
+
+    #[test]
+    fn serializes_callables_and_instances_as_strings() {
+        let source = "fun greet() {} class Point {} var point = Point();";
+
+        assert_eq!(
+            to_json(&global(source, "greet")),
+            json!("<lox fn greet>(0)")
+        );
+        assert_eq!(to_json(&global(source, "Point")), json!("Point"));
+        assert_eq!(to_json(&global(source, "point")), json!("Point instance"));
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let source =
+            json!({ "name": "lox", "version": 1, "ratio": 0.5, "tags": ["a", null, true] });
+
+        let value: LoxType = serde_json::from_value(source.clone()).unwrap();
+
+        assert_eq!(to_json(&value), source);
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    LoxError, lox_native_fn,
+    lox_type::{LoxCallableArgs, LoxInteger, LoxString, LoxType, index_arg, string_arg},
+    runtime_error,
+};
+
+pub fn get(string: &LoxString, name: &str, line: usize) -> Result<LoxType, LoxError> {
+    let s = string.clone();
+
+    let property = match name {
+        "length" => LoxType::Integer(s.chars().count() as LoxInteger),
+        "upper" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            Ok(LoxType::String(LoxString::from(s.to_uppercase())))
+        }),
+        "lower" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            Ok(LoxType::String(LoxString::from(s.to_lowercase())))
+        }),
+        "trim" => lox_native_fn!(0, move |_: LoxCallableArgs| {
+            Ok(LoxType::String(LoxString::from(s.trim())))
+        }),
+        "contains" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let needle = string_arg(&args, 0, "contains", line)?;
+            Ok(LoxType::Boolean(s.contains(&*needle)))
+        }),
+        "starts_with" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let prefix = string_arg(&args, 0, "starts_with", line)?;
+            Ok(LoxType::Boolean(s.starts_with(&*prefix)))
+        }),
+        "ends_with" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let suffix = string_arg(&args, 0, "ends_with", line)?;
+            Ok(LoxType::Boolean(s.ends_with(&*suffix)))
+        }),
+        "substring" => lox_native_fn!(2, move |(args, _, _): LoxCallableArgs| {
+            let start = index_arg(&args, 0, "substring", line)?;
+            let end = index_arg(&args, 1, "substring", line)?;
+            let length = s.chars().count();
+
+            if start > end || end > length {
+                return Err(runtime_error!(
+                    line,
+                    "Substring range {}..{} is out of bounds for a string of length {}.",
+                    start,
+                    end,
+                    length
+                ));
+            }
+
+            Ok(LoxType::String(
+                s.chars()
+                    .skip(start)
+                    .take(end - start)
+                    .collect::<String>()
+                    .into(),
+            ))
+        }),
+        "split" => lox_native_fn!(1, move |(args, _, _): LoxCallableArgs| {
+            let delimiter = string_arg(&args, 0, "split", line)?;
+
+            let parts = if delimiter.is_empty() {
+                s.chars()
+                    .map(|c| LoxType::String(LoxString::from(c.to_string())))
+                    .collect::<Vec<LoxType>>()
+            } else {
+                s.split(&*delimiter)
+                    .map(|part| LoxType::String(LoxString::from(part)))
+                    .collect::<Vec<LoxType>>()
+            };
+
+            Ok(LoxType::Array(Arc::new(Mutex::new(parts))))
+        }),
+        "replace" => lox_native_fn!(2, move |(args, _, _): LoxCallableArgs| {
+            let from = string_arg(&args, 0, "replace", line)?;
+            let to = string_arg(&args, 1, "replace", line)?;
+            Ok(LoxType::String(LoxString::from(s.replace(&*from, &to))))
+        }),
+        _ => {
+            return Err(runtime_error!(
+                line,
+                "Undefined property '{}' on string.",
+                name
+            ));
+        }
+    };
+
+    Ok(property)
+}
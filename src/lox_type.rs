@@ -1,41 +1,86 @@
 use std::{
     any::Any,
+    collections::HashMap,
     sync::{Arc, Mutex},
 };
 
 use crate::{
+    LoxError,
     environment::Environment,
-    lox_error,
-    statement::{Statement, StatementSignal},
-    token::{Keyword, Token},
+    expression::Expr,
+    runtime_error,
+    statement::{FunctionStatement, Statement, StatementSignal},
 };
 
-pub type LoxString = String;
+pub type LoxString = Arc<str>;
 pub type LoxNumber = f64;
+pub type LoxInteger = i64;
 pub type LoxBoolean = bool;
+pub type LoxArray = Arc<Mutex<Vec<LoxType>>>;
+pub type LoxMap = Arc<Mutex<HashMap<String, LoxType>>>;
 
 #[derive(Clone)]
 pub struct LoxFunction {
     pub name: String,
-    pub params: Vec<Token>,
-    pub body: Statement,
+    pub params: Arc<[(String, Option<Expr>)]>,
+    pub is_variadic: bool,
+    pub min_arity: usize,
+    pub arity: usize,
+    pub body: Arc<[Statement]>,
     pub closure: Environment,
 }
 
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Arc<LoxClass>>,
+    pub methods: HashMap<String, Arc<LoxFunction>>,
+}
+
+pub struct LoxInstance {
+    pub class: Arc<LoxClass>,
+    pub fields: HashMap<String, LoxType>,
+}
+
+pub type LoxNativeBody = dyn Fn(LoxCallableArgs) -> Result<LoxType, LoxError> + Send + Sync;
+
 #[derive(Clone)]
 pub struct LoxNativeFunction {
+    pub min_arity: usize,
     pub arity: usize,
-    pub body: Arc<dyn Fn(LoxFunctionArgs) -> LoxType + Send + Sync>,
+    pub body: Arc<LoxNativeBody>,
 }
 
 #[derive(Clone)]
 pub enum LoxType {
     String(LoxString),
     Number(LoxNumber),
+    Integer(LoxInteger),
     Boolean(LoxBoolean),
     Nil,
     Unknown,
-    Function(Arc<Mutex<dyn LoxCallable>>),
+    Function(Arc<dyn LoxCallable>),
+    Array(LoxArray),
+    Map(LoxMap),
+    Class(Arc<LoxClass>),
+    Instance(Arc<Mutex<LoxInstance>>),
+}
+
+pub fn format_number(n: LoxNumber) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+
+    if n.is_infinite() {
+        return if n > 0. { "Infinity" } else { "-Infinity" }.to_string();
+    }
+
+    let rounded = format!("{:.13e}", n).parse::<LoxNumber>().unwrap_or(n);
+
+    if rounded != 0. && (rounded.abs() >= 1e21 || rounded.abs() < 1e-7) {
+        return format!("{:e}", rounded);
+    }
+
+    rounded.to_string()
 }
 
 impl LoxType {
@@ -44,9 +89,43 @@ impl LoxType {
             LoxType::Boolean(b) => *b,
             LoxType::Nil => false,
             LoxType::Number(n) => *n != 0.,
+            LoxType::Integer(n) => *n != 0,
             _ => true,
         }
     }
+
+    /// Widens either numeric variant to a float.
+    pub fn as_number(&self) -> Option<LoxNumber> {
+        match self {
+            LoxType::Number(n) => Some(*n),
+            LoxType::Integer(n) => Some(*n as LoxNumber),
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LoxType::String(_) => "string",
+            LoxType::Number(_) | LoxType::Integer(_) => "number",
+            LoxType::Boolean(_) => "boolean",
+            LoxType::Nil => "nil",
+            LoxType::Unknown => "unknown",
+            LoxType::Function(_) => "function",
+            LoxType::Array(_) => "array",
+            LoxType::Map(_) => "map",
+            LoxType::Class(_) => "class",
+            LoxType::Instance(_) => "instance",
+        }
+    }
+}
+
+impl std::fmt::Debug for LoxType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxType::String(s) => write!(f, "{:?}", s),
+            _ => write!(f, "{}", self),
+        }
+    }
 }
 
 impl std::fmt::Display for LoxType {
@@ -54,10 +133,49 @@ impl std::fmt::Display for LoxType {
         match self {
             LoxType::Boolean(b) => write!(f, "{b}"),
             LoxType::Nil => write!(f, "nil"),
-            LoxType::Number(n) => write!(f, "{n}"),
+            LoxType::Number(n) => write!(f, "{}", format_number(*n)),
+            LoxType::Integer(n) => write!(f, "{n}"),
             LoxType::String(s) => write!(f, "{s}"),
             LoxType::Unknown => write!(f, "\0"),
-            LoxType::Function(lf) => write!(f, "<lox fn>({})", lf.lock().unwrap().arity()),
+            // A collection that is already locked is being printed further up, so it contains itself.
+            LoxType::Array(items) => {
+                let Ok(items) = items.try_lock() else {
+                    return write!(f, "[...]");
+                };
+
+                let items = items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>();
+
+                write!(f, "[{}]", items.join(", "))
+            }
+            LoxType::Map(entries) => {
+                let Ok(entries) = entries.try_lock() else {
+                    return write!(f, "{{...}}");
+                };
+
+                let mut keys = entries.keys().collect::<Vec<&String>>();
+                keys.sort();
+
+                let entries = keys
+                    .into_iter()
+                    .map(|key| format!("{}: {}", key, entries[key]))
+                    .collect::<Vec<String>>();
+
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            LoxType::Function(lf) => match lf.as_any().downcast_ref::<LoxFunction>() {
+                Some(function) if !function.name.is_empty() => {
+                    write!(f, "<lox fn {}>({})", function.name, lf.arity())
+                }
+                Some(_) => write!(f, "<lox fn>({})", lf.arity()),
+                None => write!(f, "<native fn>({})", lf.arity()),
+            },
+            LoxType::Class(class) => write!(f, "{}", class.name),
+            LoxType::Instance(instance) => {
+                write!(f, "{} instance", instance.lock().unwrap().class.name)
+            }
         }
     }
 }
@@ -67,7 +185,16 @@ impl PartialEq for LoxType {
         match (self, other) {
             (Self::String(l0), Self::String(r0)) => l0 == r0,
             (Self::Number(l0), Self::Number(r0)) => l0 == r0,
+            (Self::Integer(l0), Self::Integer(r0)) => l0 == r0,
+            (Self::Integer(_), Self::Number(_)) | (Self::Number(_), Self::Integer(_)) => {
+                self.as_number() == other.as_number()
+            }
             (Self::Boolean(l0), Self::Boolean(r0)) => l0 == r0,
+            (Self::Array(l0), Self::Array(r0)) => Arc::ptr_eq(l0, r0),
+            (Self::Map(l0), Self::Map(r0)) => Arc::ptr_eq(l0, r0),
+            (Self::Function(l0), Self::Function(r0)) => Arc::ptr_eq(l0, r0),
+            (Self::Class(l0), Self::Class(r0)) => Arc::ptr_eq(l0, r0),
+            (Self::Instance(l0), Self::Instance(r0)) => Arc::ptr_eq(l0, r0),
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
@@ -76,61 +203,468 @@ impl PartialEq for LoxType {
 pub type LoxFunctionArgs = Vec<LoxType>;
 pub type LoxCallableArgs<'a> = (LoxFunctionArgs, &'a mut Environment, usize);
 
+pub fn call_function(
+    callee: &LoxType,
+    args: LoxFunctionArgs,
+    env: &mut Environment,
+    line: usize,
+) -> Result<LoxType, LoxError> {
+    let (min_arity, arity, is_variadic) = match callee {
+        LoxType::Function(fun) => (fun.min_arity(), fun.arity(), fun.is_variadic()),
+        LoxType::Class(class) => (class.min_arity(), class.arity(), class.is_variadic()),
+        _ => return Err(runtime_error!(line, "Can only call functions and classes.")),
+    };
+
+    if args.len() < min_arity || (!is_variadic && args.len() > arity) {
+        let expected = if is_variadic {
+            format!("at least {}", min_arity)
+        } else if min_arity == arity {
+            arity.to_string()
+        } else {
+            format!("{} to {}", min_arity, arity)
+        };
+
+        return Err(runtime_error!(
+            line,
+            "Expected {} arguments but got {}.",
+            expected,
+            args.len()
+        ));
+    }
+
+    match callee {
+        LoxType::Function(fun) => fun.call((args, env, line)),
+        LoxType::Class(class) => LoxClass::instantiate(class, args, env, line),
+        _ => unreachable!(),
+    }
+}
+
+pub fn string_arg(
+    args: &LoxFunctionArgs,
+    index: usize,
+    method: &str,
+    line: usize,
+) -> Result<LoxString, LoxError> {
+    match &args[index] {
+        LoxType::String(s) => Ok(s.clone()),
+        _ => Err(runtime_error!(
+            line,
+            "Argument {} of '{}' must be a string.",
+            index + 1,
+            method
+        )),
+    }
+}
+
+pub fn number_arg(
+    args: &LoxFunctionArgs,
+    index: usize,
+    method: &str,
+    line: usize,
+) -> Result<LoxNumber, LoxError> {
+    match args[index].as_number() {
+        Some(n) => Ok(n),
+        None => Err(runtime_error!(
+            line,
+            "Argument {} of '{}' must be a number.",
+            index + 1,
+            method
+        )),
+    }
+}
+
+pub fn index_arg(
+    args: &LoxFunctionArgs,
+    index: usize,
+    method: &str,
+    line: usize,
+) -> Result<usize, LoxError> {
+    match &args[index] {
+        LoxType::Number(n) if *n >= 0. && n.fract() == 0. => Ok(*n as usize),
+        LoxType::Integer(n) if *n >= 0 => Ok(*n as usize),
+        _ => Err(runtime_error!(
+            line,
+            "Argument {} of '{}' must be a non-negative integer.",
+            index + 1,
+            method
+        )),
+    }
+}
+
 pub trait LoxCallable: Send + Sync + Any {
-    fn call(&mut self, args: LoxCallableArgs) -> LoxType;
+    fn call(&self, args: LoxCallableArgs) -> Result<LoxType, LoxError>;
 
     fn arity(&self) -> usize;
+
+    fn min_arity(&self) -> usize {
+        self.arity()
+    }
+
+    fn is_variadic(&self) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl LoxFunction {
+    pub fn new(declaration: &FunctionStatement, closure: Environment) -> Self {
+        Self {
+            name: declaration.name.clone(),
+            params: declaration
+                .params
+                .iter()
+                .map(|(name, _, default)| (name.clone(), default.clone()))
+                .collect(),
+            is_variadic: declaration.is_variadic,
+            min_arity: declaration
+                .params
+                .iter()
+                .filter(|(_, _, default)| default.is_none())
+                .count()
+                - declaration.is_variadic as usize,
+            arity: declaration.params.len() - declaration.is_variadic as usize,
+            body: Arc::from(declaration.body.as_slice()),
+            closure,
+        }
+    }
+
+    pub fn bind(&self, instance: LoxType) -> Self {
+        let mut closure = Environment::new(Some(self.closure.clone()), HashMap::new());
+        closure.define("this".to_string(), instance);
+
+        Self {
+            closure,
+            ..self.clone()
+        }
+    }
+}
+
+impl LoxClass {
+    pub fn find_method(&self, name: &str) -> Option<Arc<LoxFunction>> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(method.clone());
+        }
+
+        self.superclass
+            .as_ref()
+            .and_then(|superclass| superclass.find_method(name))
+    }
+
+    pub fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity)
+    }
+
+    pub fn min_arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.min_arity)
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        self.find_method("init")
+            .is_some_and(|init| init.is_variadic)
+    }
+
+    pub fn instantiate(
+        class: &Arc<LoxClass>,
+        args: LoxFunctionArgs,
+        env: &mut Environment,
+        line: usize,
+    ) -> Result<LoxType, LoxError> {
+        let instance = Arc::new(Mutex::new(LoxInstance {
+            class: class.clone(),
+            fields: HashMap::new(),
+        }));
+
+        let instance = LoxType::Instance(instance);
+
+        if let Some(init) = class.find_method("init") {
+            init.bind(instance.clone()).call((args, env, line))?;
+        }
+
+        Ok(instance)
+    }
+}
+
+impl LoxInstance {
+    pub fn get(
+        instance: &Arc<Mutex<LoxInstance>>,
+        name: &str,
+        line: usize,
+    ) -> Result<LoxType, LoxError> {
+        let guard = instance.lock().unwrap();
+
+        if let Some(value) = guard.fields.get(name) {
+            return Ok(value.clone());
+        }
+
+        if let Some(method) = guard.class.find_method(name) {
+            let bound = method.bind(LoxType::Instance(instance.clone()));
+            return Ok(LoxType::Function(Arc::new(bound)));
+        }
+
+        Err(runtime_error!(line, "Undefined property '{}'.", name))
+    }
+
+    pub fn set(instance: &Arc<Mutex<LoxInstance>>, name: &str, value: LoxType) {
+        instance
+            .lock()
+            .unwrap()
+            .fields
+            .insert(name.to_string(), value);
+    }
+}
+
+impl PartialEq for LoxFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.params == other.params && self.body == other.body
+    }
 }
 
 impl LoxCallable for LoxFunction {
-    fn call(&mut self, (args, env, line): LoxCallableArgs) -> LoxType {
-        let mut closure = self.closure.clone();
-        closure.define(
-            self.name.clone(),
-            LoxType::Function(Arc::new(Mutex::new(self.clone()))),
-        );
-        let mut call_env = Environment::new(Some(closure), env.values.clone());
-
-        self.params
-            .iter()
-            .enumerate()
-            .for_each(|(i, param)| match param {
-                Token::Keyword(k) => match &k.keyword {
-                    Keyword::Identifier(param_name) => {
-                        call_env.define(param_name.clone(), args[i].clone())
-                    }
-                    _ => unreachable!(),
-                },
-                _ => unreachable!(),
-            });
-
-        let res = self.body.eval(&mut call_env);
-        self.closure.reset(&call_env.enclosing.unwrap());
-
-        if res.is_ok() {
-            return LoxType::Nil;
+    fn call(&self, (args, _env, line): LoxCallableArgs) -> Result<LoxType, LoxError> {
+        let mut call_env = Environment::new(Some(self.closure.clone()), HashMap::new());
+
+        let mut args = args.into_iter();
+
+        let (fixed, rest) = match self.params.split_last() {
+            Some((rest, fixed)) if self.is_variadic => (fixed, Some(&rest.0)),
+            _ => (&self.params[..], None),
+        };
+
+        // Defaults are evaluated in the call scope so they can refer to earlier parameters.
+        for (param, default) in fixed {
+            let value = match (args.next(), default) {
+                (Some(arg), _) => arg,
+                (None, Some(default)) => default.eval(&mut call_env)?,
+                (None, None) => LoxType::Nil,
+            };
+
+            call_env.define(param.clone(), value);
         }
 
-        match res.unwrap_err() {
-            StatementSignal::Return(rv) => rv.unwrap_or(LoxType::Nil),
-            _ => lox_error!(
-                "[line {}] Error: Function terminated with an unexpected token.",
-                line
-            ),
+        if let Some(rest) = rest {
+            let rest_args = LoxType::Array(Arc::new(Mutex::new(args.collect())));
+            call_env.define(rest.clone(), rest_args);
         }
+
+        for stmt in self.body.iter() {
+            match stmt.eval(&mut call_env) {
+                Ok(()) => {}
+                Err(StatementSignal::Return(rv, _)) => return Ok(rv.unwrap_or(LoxType::Nil)),
+                Err(StatementSignal::Error(err)) => return Err(err),
+                Err(_) => {
+                    return Err(runtime_error!(
+                        line,
+                        "Function terminated with an unexpected token."
+                    ));
+                }
+            }
+        }
+
+        Ok(LoxType::Nil)
     }
 
     fn arity(&self) -> usize {
-        return self.params.len();
+        self.arity
+    }
+
+    fn min_arity(&self) -> usize {
+        self.min_arity
+    }
+
+    fn is_variadic(&self) -> bool {
+        self.is_variadic
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
 impl LoxCallable for LoxNativeFunction {
-    fn call(&mut self, (args, _env, _line): LoxCallableArgs) -> LoxType {
+    fn call(&self, args: LoxCallableArgs) -> Result<LoxType, LoxError> {
         (self.body)(args)
     }
 
     fn arity(&self) -> usize {
-        return self.arity;
+        self.arity
+    }
+
+    fn min_arity(&self) -> usize {
+        self.min_arity
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{error, eval, global};
+
+    #[test]
+    fn zero_and_negative_zero_are_falsy() {
+        assert!(!LoxType::Number(0.0).is_truthy());
+        assert!(!LoxType::Number(-0.0).is_truthy());
+    }
+
+    #[test]
+    fn nil_and_false_are_falsy() {
+        assert!(!LoxType::Nil.is_truthy());
+        assert!(!LoxType::Boolean(false).is_truthy());
+        assert!(LoxType::Boolean(true).is_truthy());
+    }
+
+    #[test]
+    fn non_zero_numbers_are_truthy() {
+        assert!(LoxType::Number(0.1).is_truthy());
+        assert!(LoxType::Number(-1.0).is_truthy());
+    }
+
+    #[test]
+    fn every_string_is_truthy() {
+        assert!(LoxType::String(LoxString::from("")).is_truthy());
+        assert!(LoxType::String(LoxString::from("false")).is_truthy());
+    }
+
+    #[test]
+    fn formats_numbers_with_fourteen_significant_digits() {
+        assert_eq!(format_number(1.0 / 3.0), "0.33333333333333");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(-0.5), "-0.5");
+    }
+
+    #[test]
+    fn formats_large_magnitudes_in_exponent_form() {
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(1e21), "1e21");
+        assert_eq!(format_number(1e300), "1e300");
+        assert_eq!(format_number(-2.5e100), "-2.5e100");
+    }
+
+    #[test]
+    fn formats_small_magnitudes_in_exponent_form() {
+        assert_eq!(format_number(1e-7), "0.0000001");
+        assert_eq!(format_number(1.5e-8), "1.5e-8");
+        assert_eq!(format_number(-1e-300), "-1e-300");
+        assert_eq!(format_number(0.0), "0");
+    }
+
+    #[test]
+    fn runs_closures() {
+        let source = "
+            fun counter() { var n = 0; fun inc() { n = n + 1; return n; } return inc; }
+            var c = counter(); c(); var result = c();
+        ";
+
+        assert_eq!(global(source, "result"), "2");
+    }
+
+    #[test]
+    fn runs_classes() {
+        let source = "
+            class P { init(x) { this.x = x; } get() { return this.x; } }
+            var result = P(7).get();
+        ";
+
+        assert_eq!(global(source, "result"), "7");
+    }
+
+    #[test]
+    fn binds_this_in_methods() {
+        let source = "
+            class Counter {
+                init() { this.count = 0; }
+                add(n) { this.count = this.count + n; return this; }
+                twice(n) { return this.add(n).add(n); }
+            }
+            var counter = Counter().add(1).twice(2);
+            var count = counter.count;
+        ";
+
+        assert_eq!(global(source, "count"), "5");
+    }
+
+    #[test]
+    fn calls_superclass_methods() {
+        let source = "
+            class A { name() { return \"A\"; } }
+            class B < A { name() { return \"B\" + super.name(); } }
+            class C < B { name() { return \"C\" + super.name(); } }
+            var single = B().name();
+            var multi = C().name();
+        ";
+
+        assert_eq!(global(source, "single"), "BA");
+        assert_eq!(global(source, "multi"), "CBA");
+    }
+
+    #[test]
+    fn inherits_methods_through_several_levels() {
+        let source = "
+            class A { greet() { return \"hi\"; } }
+            class B < A {}
+            class C < B { greet() { return super.greet() + \"!\"; } }
+            var result = C().greet();
+        ";
+
+        assert_eq!(global(source, "result"), "hi!");
+    }
+
+    #[test]
+    fn fills_in_default_parameters() {
+        let source = "
+            fun greet(name, greeting = \"Hello\") { return greeting + \" \" + name; }
+            var full = greet(\"Ann\", \"Hi\");
+            var defaulted = greet(\"Ann\");
+        ";
+
+        assert_eq!(global(source, "full"), "Hi Ann");
+        assert_eq!(global(source, "defaulted"), "Hello Ann");
+    }
+
+    #[test]
+    fn rejects_missing_arguments_without_defaults() {
+        assert_eq!(
+            error("fun g(a, b = 1) {} g();"),
+            "[line 1] Error: Expected 1 to 2 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn collects_rest_arguments() {
+        let source = "
+            fun sum(...xs) { var total = 0; for (var x in xs) total = total + x; return total; }
+            fun rest(first, ...others) { return others; }
+            var many = sum(1, 2, 3);
+            var none = rest(1);
+            var spread = sum(...[4, 5], 6);
+        ";
+
+        assert_eq!(global(source, "many"), "6");
+        assert_eq!(global(source, "none"), "[]");
+        assert_eq!(global(source, "spread"), "15");
+    }
+
+    #[test]
+    fn prints_functions_with_their_names() {
+        assert_eq!(global("fun add(a, b) {}", "add"), "<lox fn add>(2)");
+        assert_eq!(global("var f = fun (x) {};", "f"), "<lox fn>(1)");
+        assert_eq!(
+            global("class A { m() {} } var m = A().m;", "m"),
+            "<lox fn m>(0)"
+        );
+        assert_eq!(eval("clock"), "<native fn>(0)");
+    }
+
+    #[test]
+    fn prints_self_referencing_collections() {
+        assert_eq!(global("var a = [1]; a.push(a);", "a"), "[1, [...]]");
+        assert_eq!(
+            global("var m = {\"k\": 1}; m[\"m\"] = m;", "m"),
+            "{k: 1, m: {...}}"
+        );
     }
 }
@@ -1,81 +1,11 @@
-#![feature(unboxed_closures)]
-#![feature(fn_traits)]
-
-mod environment;
-mod expression;
-mod interpreter;
-mod lox_type;
-mod parser;
-mod scanner;
-mod statement;
-mod token;
-
 use std::fs;
+#[cfg(not(feature = "readline"))]
 use std::io;
+#[cfg(not(feature = "readline"))]
 use std::io::Write;
 use std::process::exit;
-use std::rc::Rc;
-use std::usize;
-
-use crate::interpreter::Interpreter;
-use crate::parser::Parser;
-use crate::scanner::Scanner;
-use crate::token::Token;
-
-#[macro_export]
-macro_rules! lox_error {
-    ($fmt:expr $(, $($arg:tt)+ )? ) => {{
-        eprintln!($fmt $(, $($arg)+ )?);
-        std::process::exit(1);
-    }};
-}
-
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
-
-#[derive(Debug)]
-pub struct CompileErrors(Vec<CompileError>);
-
-#[derive(Debug, Clone)]
-pub struct CompileError {
-    line: usize,
-    kind: String,
-    message: String,
-}
-
-impl CompileError {
-    fn new(line: usize, kind: String, message: String) -> Self {
-        Self {
-            line,
-            kind,
-            message,
-        }
-    }
-}
-
-impl std::fmt::Display for CompileError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "[line {}] Error{}: {}",
-            self.line, self.kind, self.message
-        )
-    }
-}
-
-impl std::fmt::Display for CompileErrors {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut output = String::new();
 
-        self.0.iter().for_each(|err| {
-            output.push_str(format!("{}\n", err).as_str());
-        });
-
-        write!(f, "{}", output)
-    }
-}
-
-impl std::error::Error for CompileError {}
-impl std::error::Error for CompileErrors {}
+use lox::{Interpreter, LoxType, Parser, Result, scan};
 
 fn main() -> Result<()> {
     let mut args = std::env::args();
@@ -90,19 +20,51 @@ fn main() -> Result<()> {
             eprintln!("{}", err);
             exit(1);
         }
+    } else if args.len() == 3 && args.nth(1).as_deref() == Some("--dump-ast") {
+        if let Err(err) = dump_ast(&args.next().unwrap()) {
+            eprintln!("{}", err);
+            exit(1);
+        }
     } else {
-        println!("Usage: lox [file]");
+        println!("Usage: lox [--dump-ast] [file]");
         exit(1);
     }
 
     Ok(())
 }
 
-pub fn error(line: usize, message: &String) -> CompileError {
-    CompileError::new(line, "".to_string(), String::from(message))
+#[cfg(feature = "readline")]
+fn run_prompt() -> Result<()> {
+    use rustyline::error::ReadlineError;
+
+    let mut editor = rustyline::DefaultEditor::new()?;
+    let mut interpreter = Interpreter::new();
+
+    loop {
+        let input = match editor.readline("[lox] > ") {
+            Ok(line) => line.trim_end().to_string(),
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(Box::new(err)),
+        };
+
+        if input.is_empty() {
+            break;
+        }
+
+        let _ = editor.add_history_entry(input.as_str());
+
+        if let Err(err) = run_line(&mut interpreter, &input) {
+            eprintln!("{}", err);
+        }
+    }
+
+    Ok(())
 }
 
+#[cfg(not(feature = "readline"))]
 fn run_prompt() -> Result<()> {
+    let mut interpreter = Interpreter::new();
     let mut input;
 
     loop {
@@ -119,40 +81,58 @@ fn run_prompt() -> Result<()> {
             break;
         }
 
-        run(&input)?;
+        if let Err(err) = run_line(&mut interpreter, &input) {
+            eprintln!("{}", err);
+        }
     }
 
     Ok(())
 }
 
-fn run_file(path: &String) -> Result<()> {
+fn run_line(interpreter: &mut Interpreter, input: &str) -> Result<()> {
+    // Let a bare expression such as `1 + 2` be typed without its trailing semicolon.
+    let source = if input.ends_with(';') || input.ends_with('}') {
+        input.to_string()
+    } else {
+        format!("{};", input)
+    };
+
+    for value in interpreter.run_source(&source)?.into_iter().flatten() {
+        if value != LoxType::Nil {
+            println!("{}", value);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_file(path: &str) -> Result<()> {
+    #[cfg(not(windows))]
+    let path = &path.replace('\\', "/");
+
     let file_string = fs::read_to_string(path)?;
     run(&file_string)?;
 
     Ok(())
 }
 
-fn run(source: &String) -> Result<()> {
-    let scanner = Scanner::new(source);
+fn dump_ast(path: &str) -> Result<()> {
+    #[cfg(not(windows))]
+    let path = &path.replace('\\', "/");
 
-    let errs_rc = scanner.errors.clone();
-    let tokens = scanner.collect::<Vec<Token>>();
+    let file_string = fs::read_to_string(path)?;
+    let tokens = scan(&file_string)?;
 
-    match Rc::try_unwrap(errs_rc) {
-        Ok(cell) => {
-            let errs = cell.into_inner();
-            if !errs.is_empty() {
-                return Err(Box::new(CompileErrors(errs)));
-            }
-        }
-        Err(_) => {}
+    for stmt in Parser::new(tokens).parse()? {
+        println!("{}", stmt);
     }
 
-    let mut parser = Parser::new(tokens);
-    let statements = parser.parse();
+    Ok(())
+}
 
+fn run(source: &str) -> Result<()> {
     let mut interpreter = Interpreter::new();
-    interpreter.interpret(statements);
+    interpreter.run_source(source)?;
 
-    return Ok(());
+    Ok(())
 }
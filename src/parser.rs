@@ -1,194 +1,355 @@
 use crate::{
+    CompileError, CompileErrors, LoxError,
     expression::{
-        AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, LiteralExpr, LiteralExprType,
-        LogicalExpr, TernaryExpr, UnaryExpr, VariableExpr,
+        ArrayExpr, AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr,
+        IndexExpr, IndexSetExpr, LiteralExpr, LiteralExprType, LogicalExpr, LogicalOp, MapExpr,
+        NilCoalesceExpr, OptionalGetExpr, SetExpr, SpreadExpr, SuperExpr, TernaryExpr, ThisExpr,
+        UnaryExpr, UpdateExpr, VariableExpr,
     },
-    lox_error,
+    lox_type::LoxString,
+    parse_error,
+    resolver::Resolver,
     statement::{
-        FunctionStatement, IfStatement, ReturnStatement, Statement, VarStatement, WhileStatement,
+        ClassStatement, DoWhileStatement, ForInStatement, FunctionStatement, IfStatement,
+        JumpStatement, LabeledStatement, ReturnStatement, Statement, SwitchStatement,
+        ThrowStatement, TryStatement, VarStatement, WhileStatement,
     },
-    token::{Keyword, Token},
+    token::{Keyword, Token, TokenValue},
+    token_cursor::TokenCursor,
 };
 
 macro_rules! consume {
-    ($self:ident, $($token_type:ident)|+, $msg:expr) => {{
-        if let Some(token) = $self.tokens.get($self.current) {
-            match token {
-                $(Token::$token_type(_))|+ => {
-                    $self.current += 1;
-                    token
-                },
-                _ => lox_error!(concat!("[line {}] ", $msg), token.line() - 1),
-            }
-        } else {
-            lox_error!(concat!("Internal error: ", $msg));
+    ($self:ident, $($token_type:ident)|+, $msg:literal) => {{
+        let token = $self.cursor.peek();
+
+        match token {
+            $(Token::$token_type(_))|+ => $self.cursor.advance(),
+            _ => return Err(parse_error!($self.cursor.previous().line(), $msg)),
         }
     }};
 
-    ($self:ident, Keyword, Identifier, $msg:expr) => {{
-        if let Some(token) = $self.tokens.get($self.current) {
-            match token {
-                Token::Keyword(inner) => match &inner.keyword {
-                    Keyword::Identifier(_) => {
-                        $self.current += 1;
-                        token
-                    },
-                    _ => lox_error!(concat!("[line {}] ", $msg), token.line() - 1),
-                },
-                _ => lox_error!(concat!("[line {}] ", $msg), token.line() - 1),
-            }
-        } else {
-            lox_error!(concat!("Internal error: ", $msg));
+    ($self:ident, Keyword, Identifier, $msg:literal) => {{
+        let token = $self.cursor.peek();
+
+        match token {
+            Token::Keyword(inner) => match &inner.keyword {
+                Keyword::Identifier(_) => $self.cursor.advance(),
+                _ => return Err(parse_error!($self.cursor.previous().line(), $msg)),
+            },
+            _ => return Err(parse_error!($self.cursor.previous().line(), $msg)),
         }
     }};
 
-    ($self:ident, Keyword, $inner:ident, $msg:expr) => {{
-        if let Some(token) = $self.tokens.get($self.current) {
-            match token {
-                Token::Keyword(inner) => match &inner.keyword {
-                    Keyword::$inner => {
-                        $self.current += 1;
-                        token
-                    },
-                    _ => lox_error!(concat!("[line {}] ", $msg), token.line() - 1),
-                },
-                _ => lox_error!(concat!("[line {}] ", $msg), token.line() - 1),
-            }
-        } else {
-            lox_error!(concat!("Internal error: ", $msg));
+    ($self:ident, Keyword, $inner:ident, $msg:literal) => {{
+        let token = $self.cursor.peek();
+
+        match token {
+            Token::Keyword(inner) => match &inner.keyword {
+                Keyword::$inner => $self.cursor.advance(),
+                _ => return Err(parse_error!($self.cursor.previous().line(), $msg)),
+            },
+            _ => return Err(parse_error!($self.cursor.previous().line(), $msg)),
         }
     }};
 }
 
+// Compound operators such as `%=` are always scanned as a single token, so
+// peek_token! and match_token! only ever need to look at the current token.
+macro_rules! peek_token {
+    ($self:ident, $($token_type:ident)|+) => {{
+        $self.cursor.check(|token| matches!(token, $(Token::$token_type(_))|+))
+    }};
+
+    ($self:ident, $token_type:ident, $($inner_enum:ident)|+) => {{
+        $self.cursor.check(|token| match token {
+            Token::$token_type(inner) => matches!(inner.keyword, $(Keyword::$inner_enum)|+),
+            _ => false,
+        })
+    }};
+}
+
 macro_rules! match_token {
     ($self:ident, $($token_type:ident)|+) => {{
-        if let Some(token) = $self.tokens.get($self.current) {
-            if matches!(token, $(Token::$token_type(_))|+) {
-                $self.current += 1;
-                Some(token)
-            } else {
-                None
-            }
+        if peek_token!($self, $($token_type)|+) {
+            Some($self.cursor.advance())
         } else {
             None
         }
     }};
 
     ($self:ident, $token_type:ident, $($inner_enum:ident)|+) => {{
-        if let Some(token) = $self.tokens.get($self.current) {
-            match token {
-                Token::$token_type(inner) => {
-                    let matched = matches!(inner.keyword, $(Keyword::$inner_enum)|+);
-                    if matched {
-                        $self.current += 1;
-                        Some(token)
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            }
+        if peek_token!($self, $token_type, $($inner_enum)|+) {
+            Some($self.cursor.advance())
         } else {
             None
         }
     }};
 }
 
+fn operator_value(tv: &TokenValue, lexeme: &str) -> Box<TokenValue> {
+    Box::new(TokenValue {
+        lexeme: lexeme.to_string(),
+        line: tv.line,
+        col: tv.col,
+    })
+}
+
+// The `case` clauses of a switch and its optional `default` clause.
+type SwitchClauses = (Vec<(Expr, Vec<Statement>)>, Option<Vec<Statement>>);
+
+#[derive(PartialEq)]
+enum ClassKind {
+    Class,
+    Subclass,
+}
+
 pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
+    cursor: TokenCursor,
+    loop_depth: usize,
+    switch_depth: usize,
+    labels: Vec<String>,
+    classes: Vec<ClassKind>,
+    errors: Vec<CompileError>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            cursor: TokenCursor::new(tokens),
+            loop_depth: 0,
+            switch_depth: 0,
+            labels: vec![],
+            classes: vec![],
+            errors: vec![],
+        }
     }
 
-    fn expression(&mut self) -> Expr {
-        return self.assignment();
+    fn expression(&mut self) -> Result<Expr, LoxError> {
+        self.assignment()
     }
 
-    fn assignment(&mut self) -> Expr {
-        let expr = self.or();
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.ternary()?;
 
         if let Some(token) = match_token!(self, Equal) {
             let line = token.line();
-            let value = self.assignment();
+            let value = self.assignment()?;
 
             match expr {
                 Expr::Variable(v) => {
-                    return Expr::Assign(AssignExpr {
+                    return Ok(Expr::Assign(AssignExpr {
                         name: v.name,
                         value: Box::new(value),
-                    });
+                        line,
+                        depth: None,
+                    }));
                 }
-                _ => {
-                    lox_error!("[line {}] Error: Invalid assignment target.", line)
+                Expr::Get(g) => {
+                    return Ok(Expr::Set(SetExpr {
+                        object: g.object,
+                        name: g.name,
+                        value: Box::new(value),
+                    }));
+                }
+                Expr::Index(i) => {
+                    return Ok(Expr::IndexSet(IndexSetExpr {
+                        object: i.object,
+                        bracket: i.bracket,
+                        index: i.index,
+                        value: Box::new(value),
+                    }));
                 }
+                _ => return Err(parse_error!(line, "Invalid assignment target.")),
             }
         }
 
-        return expr;
+        if let Some(token) = match_token!(
+            self,
+            PlusEqual
+                | MinusEqual
+                | StarEqual
+                | SlashEqual
+                | PercentEqual
+                | StarStarEqual
+                | AmpersandEqual
+                | BitwiseOrEqual
+                | CaretEqual
+                | ShiftLeftEqual
+                | ShiftRightEqual
+        ) {
+            let operator = match token {
+                Token::PlusEqual(tv) => Token::Plus(operator_value(tv, "+")),
+                Token::MinusEqual(tv) => Token::Minus(operator_value(tv, "-")),
+                Token::StarEqual(tv) => Token::Star(operator_value(tv, "*")),
+                Token::SlashEqual(tv) => Token::Slash(operator_value(tv, "/")),
+                Token::PercentEqual(tv) => Token::Percent(operator_value(tv, "%")),
+                Token::StarStarEqual(tv) => Token::StarStar(operator_value(tv, "**")),
+                Token::AmpersandEqual(tv) => Token::Ampersand(operator_value(tv, "&")),
+                Token::BitwiseOrEqual(tv) => Token::BitwiseOr(operator_value(tv, "|")),
+                Token::CaretEqual(tv) => Token::Caret(operator_value(tv, "^")),
+                Token::ShiftLeftEqual(tv) => Token::ShiftLeft(operator_value(tv, "<<")),
+                Token::ShiftRightEqual(tv) => Token::ShiftRight(operator_value(tv, ">>")),
+                _ => unreachable!(),
+            };
+
+            return self.compound_assignment(expr, operator);
+        }
+
+        if let Some(token) = match_token!(self, AmpAmpEqual | PipePipeEqual | QuestionQuestionEqual)
+        {
+            let token = token.clone();
+            return self.logical_assignment(expr, token);
+        }
+
+        Ok(expr)
     }
 
-    fn or(&mut self) -> Expr {
-        let mut expr = self.and();
+    // `x &&= y` becomes `x and (x = y)`, so `y` is only evaluated when the assignment happens.
+    fn logical_assignment(&mut self, target: Expr, operator: Token) -> Result<Expr, LoxError> {
+        let line = operator.line();
+        let value = self.assignment()?;
 
-        while let Some(op) = match_token!(self, Keyword, Or) {
-            let operator = op.clone();
-            let right = self.and();
+        let Expr::Variable(v) = target else {
+            return Err(parse_error!(line, "Invalid assignment target."));
+        };
+
+        let assign = Box::new(Expr::Assign(AssignExpr {
+            name: v.name.clone(),
+            value: Box::new(value),
+            line,
+            depth: None,
+        }));
+        let left = Box::new(Expr::Variable(v));
+
+        Ok(match operator {
+            Token::AmpAmpEqual(_) => Expr::Logical(LogicalExpr {
+                left,
+                operator: LogicalOp::And,
+                right: assign,
+            }),
+            Token::PipePipeEqual(_) => Expr::Logical(LogicalExpr {
+                left,
+                operator: LogicalOp::Or,
+                right: assign,
+            }),
+            _ => Expr::NilCoalesce(NilCoalesceExpr {
+                left,
+                right: assign,
+            }),
+        })
+    }
+
+    fn compound_assignment(&mut self, target: Expr, operator: Token) -> Result<Expr, LoxError> {
+        let line = operator.line();
+        let value = self.assignment()?;
+
+        match target {
+            Expr::Variable(v) => Ok(Expr::Assign(AssignExpr {
+                name: v.name.clone(),
+                value: Box::new(Expr::Binary(BinaryExpr {
+                    left: Box::new(Expr::Variable(v)),
+                    operator,
+                    right: Box::new(value),
+                })),
+                line,
+                depth: None,
+            })),
+            _ => Err(parse_error!(line, "Invalid assignment target.")),
+        }
+    }
+
+    fn ternary(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.nil_coalesce()?;
+
+        while let Some(token) = match_token!(self, QuestionMark) {
+            let line = token.line();
+            let trueish = self.ternary()?;
+            consume!(self, Colon, "Missing ':' in ternary expression.");
+            let falseish = self.ternary()?;
+
+            expr = Expr::Ternary(TernaryExpr {
+                condition: Box::new(expr),
+                trueish: Box::new(trueish),
+                falseish: Box::new(falseish),
+                line,
+            })
+        }
+
+        Ok(expr)
+    }
+
+    fn nil_coalesce(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.or()?;
+
+        while match_token!(self, QuestionQuestion).is_some() {
+            let right = self.or()?;
+
+            expr = Expr::NilCoalesce(NilCoalesceExpr {
+                left: Box::new(expr),
+                right: Box::new(right),
+            })
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.and()?;
+
+        while match_token!(self, Keyword, Or).is_some() {
+            let right = self.and()?;
 
             expr = Expr::Logical(LogicalExpr {
                 left: Box::new(expr),
-                operator,
+                operator: LogicalOp::Or,
                 right: Box::new(right),
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn and(&mut self) -> Expr {
-        let mut expr = self.ternary();
+    fn and(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.bitwise()?;
 
-        while let Some(op) = match_token!(self, Keyword, And) {
-            let operator = op.clone();
-            let right = self.ternary();
+        while match_token!(self, Keyword, And).is_some() {
+            let right = self.bitwise()?;
 
             expr = Expr::Logical(LogicalExpr {
                 left: Box::new(expr),
-                operator,
+                operator: LogicalOp::And,
                 right: Box::new(right),
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn ternary(&mut self) -> Expr {
-        let mut expr = self.equality();
+    fn bitwise(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.equality()?;
 
-        while let Some(_) = match_token!(self, QuestionMark) {
-            let trueish = self.ternary();
-            consume!(self, Colon, "Error: Missing ':' in ternary expression.");
-            let falseish = self.ternary();
+        while let Some(op) =
+            match_token!(self, Ampersand | BitwiseOr | Caret | ShiftLeft | ShiftRight)
+        {
+            let operator = op.clone();
+            let right = self.equality()?;
 
-            expr = Expr::Ternary(TernaryExpr {
-                condition: Box::new(expr),
-                trueish: Box::new(trueish),
-                falseish: Box::new(falseish),
+            expr = Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    fn equality(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.comparison()?;
 
         while let Some(op) = match_token!(self, BangEqual | EqualEqual) {
             let operator = op.clone();
-            let right = self.comparison();
+            let right = self.comparison()?;
 
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -197,15 +358,15 @@ impl Parser {
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn comparison(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.term()?;
 
         while let Some(op) = match_token!(self, Greater | GreaterEqual | Less | LessEqual) {
             let operator = op.clone();
-            let right = self.term();
+            let right = self.term()?;
 
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -214,15 +375,15 @@ impl Parser {
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
+    fn term(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.factor()?;
 
         while let Some(op) = match_token!(self, Minus | Plus) {
             let operator = op.clone();
-            let right = self.factor();
+            let right = self.factor()?;
 
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -231,15 +392,15 @@ impl Parser {
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn factor(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.unary()?;
 
-        while let Some(op) = match_token!(self, Slash | Star) {
+        while let Some(op) = match_token!(self, Slash | Star | Percent) {
             let operator = op.clone();
-            let right = self.unary();
+            let right = self.unary()?;
 
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -248,428 +409,1529 @@ impl Parser {
             })
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Expr {
-        if let Some(op) = match_token!(self, Bang | Minus) {
+    fn unary(&mut self) -> Result<Expr, LoxError> {
+        if let Some(op) = match_token!(self, Bang | Minus | Tilde) {
             let operator = op.clone();
-            let right = self.unary();
+            let right = self.unary()?;
 
-            return Expr::Unary(UnaryExpr {
+            return Ok(Expr::Unary(UnaryExpr {
                 operator,
                 right: Box::new(right),
-            });
+            }));
+        }
+
+        if let Some(op) = match_token!(self, Keyword, Typeof) {
+            let operator = op.clone();
+            let right = self.unary()?;
+
+            return Ok(Expr::Unary(UnaryExpr {
+                operator,
+                right: Box::new(right),
+            }));
+        }
+
+        if let Some(op) = match_token!(self, PlusPlus | MinusMinus) {
+            let operator = op.clone();
+            let target = self.unary()?;
+
+            return self.update(target, operator, true);
+        }
+
+        self.exponent()
+    }
+
+    fn exponent(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.postfix()?;
+
+        if let Some(op) = match_token!(self, StarStar) {
+            let operator = op.clone();
+            let right = self.unary()?;
+
+            return Ok(Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }));
         }
 
-        return self.call();
+        Ok(expr)
     }
 
-    fn call(&mut self) -> Expr {
-        let mut expr = self.primary();
+    fn postfix(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.call()?;
+
+        if let Some(op) = match_token!(self, PlusPlus | MinusMinus) {
+            let operator = op.clone();
+
+            return self.update(expr, operator, false);
+        }
+
+        Ok(expr)
+    }
+
+    fn update(&mut self, target: Expr, operator: Token, prefix: bool) -> Result<Expr, LoxError> {
+        if !matches!(target, Expr::Variable(_) | Expr::Get(_) | Expr::Index(_)) {
+            return Err(parse_error!(operator.line(), "Invalid increment target."));
+        }
+
+        Ok(Expr::Update(UpdateExpr {
+            target: Box::new(target),
+            operator,
+            prefix,
+        }))
+    }
+
+    fn call(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.primary()?;
 
         loop {
-            if let Some(_) = match_token!(self, LeftParen) {
-                expr = self.finish_call(expr);
+            if match_token!(self, LeftParen).is_some() {
+                expr = self.finish_call(expr)?;
+            } else if match_token!(self, Dot).is_some() {
+                let name = consume!(
+                    self,
+                    Keyword,
+                    Identifier,
+                    "Expected property name after '.'."
+                );
+
+                expr = Expr::Get(GetExpr {
+                    object: Box::new(expr),
+                    name: name.clone(),
+                });
+            } else if match_token!(self, QuestionDot).is_some() {
+                let name = consume!(
+                    self,
+                    Keyword,
+                    Identifier,
+                    "Expected property name after '?.'."
+                );
+
+                expr = Expr::OptionalGet(OptionalGetExpr {
+                    object: Box::new(expr),
+                    name: name.clone(),
+                });
+            } else if let Some(token) = match_token!(self, LeftBracket) {
+                let bracket = token.clone();
+                let index = self.expression()?;
+                consume!(self, RightBracket, "Expected ']' after index.");
+
+                expr = Expr::Index(IndexExpr {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
             } else {
                 break;
             }
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Expr {
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, LoxError> {
         let mut args = vec![];
 
-        if let Some(token) = self.tokens.get(self.current) {
-            let line = token.line();
+        let line = self.cursor.peek().line();
 
-            match token {
-                Token::RightParen(_) => {}
-                _ => loop {
-                    if args.len() >= 255 {
-                        lox_error!(
-                            "[line {}] Error: Cannot have more than 255 arguments.",
-                            line
-                        );
-                    }
+        if !peek_token!(self, RightParen) {
+            loop {
+                if args.len() >= 255 {
+                    return Err(parse_error!(line, "Can't have more than 255 arguments."));
+                }
 
-                    args.push(self.expression());
+                if let Some(token) = match_token!(self, DotDotDot) {
+                    let token = token.clone();
+                    let expression = self.expression()?;
 
-                    if match_token!(self, Comma).is_none() {
-                        break;
-                    }
-                },
+                    args.push(Expr::Spread(SpreadExpr {
+                        token,
+                        expression: Box::new(expression),
+                    }));
+                } else {
+                    args.push(self.expression()?);
+                }
+
+                if match_token!(self, Comma).is_none() {
+                    break;
+                }
             }
         }
 
-        let paren = consume!(self, RightParen, "Error: Expect ')' after arguments.");
+        let paren = consume!(self, RightParen, "Expect ')' after arguments.");
 
-        return Expr::Call(CallExpr {
+        Ok(Expr::Call(CallExpr {
             arguments: args,
             paren: paren.clone(),
             callee: Box::new(callee),
-        });
+        }))
     }
 
-    fn primary(&mut self) -> Expr {
-        if let Some(token) = self.tokens.get(self.current) {
-            match token {
-                Token::Keyword(id) => match &id.keyword {
-                    Keyword::False | Keyword::True | Keyword::Nil => {
-                        self.current += 1;
+    fn primary(&mut self) -> Result<Expr, LoxError> {
+        let token = self.cursor.peek().clone();
 
-                        return Expr::Literal(LiteralExpr {
-                            value: LiteralExprType::Identifier(id.keyword.clone()),
-                        });
-                    }
-                    Keyword::Identifier(name) => {
-                        self.current += 1;
+        match &token {
+            Token::Keyword(id) => match &id.keyword {
+                Keyword::False | Keyword::True | Keyword::Nil => {
+                    self.cursor.advance();
 
-                        return Expr::Variable(VariableExpr { name: name.clone() });
+                    Ok(Expr::Literal(LiteralExpr {
+                        value: LiteralExprType::Identifier(id.keyword.clone()),
+                    }))
+                }
+                Keyword::Super => {
+                    match self.classes.last() {
+                        None => {
+                            return Err(parse_error!(
+                                id.line,
+                                "Can't use 'super' outside of a class."
+                            ));
+                        }
+                        Some(ClassKind::Class) => {
+                            return Err(parse_error!(
+                                id.line,
+                                "Can't use 'super' in a class with no superclass."
+                            ));
+                        }
+                        Some(ClassKind::Subclass) => {}
                     }
-                    _ => {
-                        lox_error!(
-                            "[line {}] Error: Unexpected identifier '{}' encountered.",
+
+                    self.cursor.advance();
+                    consume!(self, Dot, "Expected '.' after 'super'.");
+                    let method = consume!(
+                        self,
+                        Keyword,
+                        Identifier,
+                        "Expected superclass method name."
+                    )
+                    .clone();
+
+                    Ok(Expr::Super(SuperExpr {
+                        keyword: token.clone(),
+                        method,
+                    }))
+                }
+                Keyword::This => {
+                    if self.classes.is_empty() {
+                        return Err(parse_error!(
                             id.line,
-                            id.keyword
-                        );
+                            "Can't use 'this' outside of a class."
+                        ));
                     }
-                },
-                Token::Number(num) => {
-                    self.current += 1;
 
-                    return Expr::Literal(LiteralExpr {
-                        value: LiteralExprType::Number(num.value),
-                    });
-                }
-                Token::String(str) => {
-                    self.current += 1;
+                    self.cursor.advance();
 
-                    return Expr::Literal(LiteralExpr {
-                        value: LiteralExprType::String(str.value.clone()),
-                    });
+                    Ok(Expr::This(ThisExpr {
+                        keyword: token.clone(),
+                    }))
                 }
-                Token::LeftParen(_) => {
-                    self.current += 1;
-
-                    let expr = self.expression();
+                Keyword::Fun => {
+                    self.cursor.advance();
+                    consume!(self, LeftParen, "Expected '(' after 'fun'.");
 
-                    consume!(self, RightParen, "Error: Missing ')'.");
+                    let function = self.function_body(String::new(), id.line)?;
 
-                    return Expr::Grouping(GroupingExpr {
-                        expression: Box::new(expr),
-                    });
+                    Ok(Expr::Function(FunctionExpr { function }))
                 }
-                Token::Eof(_) => {
-                    return Expr::Literal(LiteralExpr {
-                        value: LiteralExprType::EOF,
-                    });
+                Keyword::Identifier(name) => {
+                    self.cursor.advance();
+
+                    Ok(Expr::Variable(VariableExpr {
+                        name: name.clone(),
+                        line: id.line,
+                        depth: None,
+                    }))
                 }
                 _ => {
-                    lox_error!("Unexpected token {} encountered.", token);
+                    if matches!(self.cursor.peek_next(), Token::Equal(_)) {
+                        return Err(parse_error!(
+                            id.line,
+                            "'{}' is a reserved word and cannot be used as an identifier.",
+                            id.keyword
+                        ));
+                    }
+
+                    Err(parse_error!(
+                        id.line,
+                        "Keyword '{}' cannot start an expression.",
+                        id.keyword
+                    ))
                 }
+            },
+            Token::Number(num) => {
+                self.cursor.advance();
+
+                let value = match num.integer {
+                    Some(integer) => LiteralExprType::Integer(integer),
+                    None => LiteralExprType::Number(num.value),
+                };
+
+                Ok(Expr::Literal(LiteralExpr { value }))
             }
-        }
+            Token::String(str) => {
+                self.cursor.advance();
 
-        lox_error!("Empty file cannot be parsed.");
-    }
+                Ok(Expr::Literal(LiteralExpr {
+                    value: LiteralExprType::String(str.value.clone()),
+                }))
+            }
+            Token::LeftParen(_) => {
+                self.cursor.advance();
 
-    fn declaration(&mut self) -> Statement {
-        if let Some(_) = match_token!(self, Keyword, Fun) {
-            return self.function("function");
-        }
+                let expr = self.expression()?;
 
-        if let Some(_) = match_token!(self, Keyword, Var) {
-            return self.var_declaration();
-        }
+                consume!(self, RightParen, "Missing ')'.");
 
-        return self.statement();
-    }
+                Ok(Expr::Grouping(GroupingExpr {
+                    expression: Box::new(expr),
+                }))
+            }
+            Token::LeftBrace(_) => {
+                self.cursor.advance();
+
+                let mut pairs = vec![];
+
+                if !peek_token!(self, RightBrace) {
+                    loop {
+                        // A bare identifier key such as `{name: 1}` is taken as a string.
+                        let bare_key = match (self.cursor.peek(), self.cursor.peek_next()) {
+                            (Token::Keyword(k), Token::Colon(_)) => match &k.keyword {
+                                Keyword::Identifier(name) => Some(name.clone()),
+                                _ => None,
+                            },
+                            _ => None,
+                        };
 
-    fn function(&mut self, _kind: &str) -> Statement {
-        let name = consume!(self, Keyword, Identifier, "Error: Expected function name.");
-        consume!(self, LeftParen, "Error: Expected '(' after function name.");
+                        let key = match bare_key {
+                            Some(name) => {
+                                self.cursor.advance();
 
-        let mut parameters = vec![];
-        if let Some(token) = self.tokens.get(self.current) {
-            let line = token.line();
+                                Expr::Literal(LiteralExpr {
+                                    value: LiteralExprType::String(LoxString::from(name)),
+                                })
+                            }
+                            None => self.expression()?,
+                        };
+
+                        consume!(self, Colon, "Expected ':' after map key.");
+                        let value = self.expression()?;
+                        pairs.push((key, value));
 
-            match token {
-                Token::RightParen(_) => {}
-                _ => loop {
-                    if parameters.len() > 255 {
-                        lox_error!(
-                            "[line {}] Error: Can't have more than 255 parameters.",
-                            line
-                        );
+                        if match_token!(self, Comma).is_none() {
+                            break;
+                        }
                     }
+                }
+
+                consume!(self, RightBrace, "Expected '}}' after map entries.");
 
-                    parameters.push(
-                        consume!(self, Keyword, Identifier, "Error: Expected parameter name.")
-                            .clone(),
-                    );
+                Ok(Expr::Map(MapExpr {
+                    brace: token.clone(),
+                    pairs,
+                }))
+            }
+            Token::LeftBracket(_) => {
+                self.cursor.advance();
+
+                let mut elements = vec![];
 
-                    if match_token!(self, Comma).is_none() {
-                        break;
+                if !peek_token!(self, RightBracket) {
+                    loop {
+                        elements.push(self.expression()?);
+
+                        if match_token!(self, Comma).is_none() {
+                            break;
+                        }
                     }
-                },
+                }
+
+                consume!(self, RightBracket, "Expected ']' after array elements.");
+
+                Ok(Expr::Array(ArrayExpr { elements }))
             }
+            Token::Eof(_) => Err(parse_error!(
+                self.cursor.previous().line(),
+                "Expect expression."
+            )),
+            _ => Err(parse_error!(
+                token.line(),
+                "Unexpected token {} encountered.",
+                token
+            )),
+        }
+    }
+
+    fn declaration(&mut self) -> Result<Statement, LoxError> {
+        if let Some(token) = match_token!(self, Keyword, Class) {
+            let line = token.line();
+            return self.class_declaration(line);
         }
 
-        consume!(self, RightParen, "Error: Expected ')' after parameters.");
-        consume!(
-            self,
-            LeftBrace,
-            "Error: Expected '{{' before function body."
+        // `fun` followed by anything but a name starts a function expression.
+        let is_named = matches!(
+            self.cursor.peek_next(),
+            Token::Keyword(k) if matches!(k.keyword, Keyword::Identifier(_))
         );
 
+        if is_named && match_token!(self, Keyword, Fun).is_some() {
+            return Ok(Statement::Function(self.function()?));
+        }
+
+        if match_token!(self, Keyword, Var).is_some() {
+            return self.var_declaration(false);
+        }
+
+        if match_token!(self, Keyword, Const).is_some() {
+            return self.var_declaration(true);
+        }
+
+        self.statement()
+    }
+
+    fn class_declaration(&mut self, line: usize) -> Result<Statement, LoxError> {
+        let name = consume!(self, Keyword, Identifier, "Expected class name.");
+
         let name = match name {
             Token::Keyword(k) => match &k.keyword {
-                Keyword::Identifier(n) => n,
+                Keyword::Identifier(n) => n.clone(),
                 _ => unreachable!(),
             },
             _ => unreachable!(),
         };
 
-        return Statement::Function(FunctionStatement {
-            name: name.clone(),
-            params: parameters,
-            body: Box::new(Statement::Block(self.block())),
-        });
-    }
-
-    fn var_declaration(&mut self) -> Statement {
-        if let Some(token) = self.tokens.get(self.current) {
+        let mut superclass = None;
+        if let Some(token) = match_token!(self, Less) {
             let line = token.line();
+            let superclass_name = consume!(self, Keyword, Identifier, "Expected superclass name.");
 
-            match token {
+            let superclass_name = match superclass_name {
                 Token::Keyword(k) => match &k.keyword {
-                    Keyword::Identifier(name) => {
-                        let n = name.clone();
+                    Keyword::Identifier(n) => n.clone(),
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            };
 
-                        self.current += 1;
+            if superclass_name == name {
+                return Err(parse_error!(line, "A class can't inherit from itself."));
+            }
 
-                        let initializer = if let Some(_) = match_token!(self, Equal) {
-                            Some(self.expression())
-                        } else {
-                            None
-                        };
+            superclass = Some(VariableExpr {
+                name: superclass_name,
+                line,
+                depth: None,
+            });
+        }
 
-                        consume!(self, Semicolon, "Error: Missing ';'.");
+        consume!(self, LeftBrace, "Expected '{{' before class body.");
 
-                        return Statement::Var(VarStatement {
-                            name: n,
-                            initializer,
-                        });
-                    }
-                    _ => lox_error!(
-                        "[line {}] Error: The name of your variable cannot be a keyword.",
-                        line
-                    ),
-                },
-                _ => lox_error!("[line {}] Error: Provide a name for your variable.", line),
-            }
-        }
+        let kind = if superclass.is_some() {
+            ClassKind::Subclass
+        } else {
+            ClassKind::Class
+        };
 
-        lox_error!(
-            "[line {}] Error: Provide a name for your variable.",
-            self.tokens.last().unwrap().line()
-        );
-    }
+        let methods = self.with_class(kind, |parser| {
+            let mut methods = vec![];
 
-    fn statement(&mut self) -> Statement {
-        if let Some(_) = match_token!(self, Keyword, For) {
-            return self.for_statement();
-        }
+            while !peek_token!(parser, RightBrace | Eof) {
+                methods.push(parser.function()?);
+            }
 
-        if let Some(_) = match_token!(self, Keyword, Print) {
-            return self.print_statement();
-        }
+            Ok(methods)
+        })?;
 
-        if let Some(token) = match_token!(self, Keyword, Return) {
-            return self.return_statement(token.clone());
-        }
+        consume!(self, RightBrace, "Expected '}}' after class body.");
 
-        if let Some(_) = match_token!(self, Keyword, While) {
-            return self.while_statement();
-        }
+        Ok(Statement::Class(ClassStatement {
+            name,
+            superclass,
+            methods,
+            line,
+        }))
+    }
 
-        if let Some(_) = match_token!(self, Keyword, If) {
-            return self.if_statement();
-        }
+    fn function(&mut self) -> Result<FunctionStatement, LoxError> {
+        let name = consume!(self, Keyword, Identifier, "Expected function name.");
 
-        if let Some(_) = match_token!(self, Keyword, Break) {
-            return self.break_statement();
-        }
+        let (name, name_line) = match name {
+            Token::Keyword(k) => match &k.keyword {
+                Keyword::Identifier(n) => (n.clone(), k.line),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
 
-        if let Some(_) = match_token!(self, Keyword, Continue) {
-            return self.continue_statement();
-        }
-        if let Some(_) = match_token!(self, LeftBrace) {
-            return Statement::Block(self.block());
-        }
+        consume!(self, LeftParen, "Expected '(' after function name.");
 
-        return self.expression_statement();
+        self.function_body(name, name_line)
     }
 
-    fn block(&mut self) -> Vec<Statement> {
-        let mut statements = vec![];
+    fn function_body(
+        &mut self,
+        name: String,
+        name_line: usize,
+    ) -> Result<FunctionStatement, LoxError> {
+        let mut parameters: Vec<(String, usize, Option<Expr>)> = vec![];
+        let mut is_variadic = false;
+        let line = self.cursor.peek().line();
+
+        if !peek_token!(self, RightParen) {
+            loop {
+                if parameters.len() >= 255 {
+                    return Err(parse_error!(line, "Can't have more than 255 parameters."));
+                }
 
-        while let Some(token) = self.tokens.get(self.current) {
-            if let Token::RightBrace(_) | Token::Eof(_) = token {
-                break;
-            }
+                if is_variadic {
+                    return Err(parse_error!(
+                        line,
+                        "Rest parameter must be the last parameter."
+                    ));
+                }
 
-            statements.push(self.declaration());
-        }
+                is_variadic = match_token!(self, DotDotDot).is_some();
 
-        consume!(self, RightBrace, "Error: Missing '}}'.");
+                let param = consume!(self, Keyword, Identifier, "Expected parameter name.");
 
-        return statements;
-    }
+                let (param, param_line) = match param {
+                    Token::Keyword(k) => match &k.keyword {
+                        Keyword::Identifier(n) => (n.clone(), k.line),
+                        _ => unreachable!(),
+                    },
+                    _ => unreachable!(),
+                };
 
-    fn for_statement(&mut self) -> Statement {
-        consume!(self, LeftParen, "Error: Expect '(' after 'for'.");
+                let mut default = None;
 
-        let initializer;
-        if let Some(_) = match_token!(self, Semicolon) {
-            initializer = None;
-        } else if let Some(_) = match_token!(self, Keyword, Var) {
-            initializer = Some(self.var_declaration());
-        } else {
-            initializer = Some(self.expression_statement());
-        }
+                if is_variadic && peek_token!(self, Equal) {
+                    return Err(parse_error!(
+                        param_line,
+                        "Rest parameter '{}' cannot have a default.",
+                        param
+                    ));
+                }
 
-        let mut condition = None;
-        if let Some(token) = self.tokens.get(self.current) {
-            match token {
-                Token::Semicolon(_) => {}
-                _ => condition = Some(self.expression()),
-            }
-        }
-        consume!(self, Semicolon, "Error: Expect ';' after loop condition.");
+                if match_token!(self, Equal).is_some() {
+                    default = Some(self.ternary()?);
+                } else if !is_variadic && parameters.iter().any(|(_, _, default)| default.is_some())
+                {
+                    return Err(parse_error!(
+                        param_line,
+                        "Parameter '{}' without a default cannot follow one with a default.",
+                        param
+                    ));
+                }
 
-        let mut increment = None;
-        if let Some(token) = self.tokens.get(self.current) {
-            match token {
-                Token::RightParen(_) => {}
-                _ => increment = Some(self.expression()),
+                parameters.push((param, param_line, default));
+
+                if match_token!(self, Comma).is_none() {
+                    break;
+                }
             }
         }
-        consume!(self, RightParen, "Error: Expect ')' after for clauses.");
 
-        let mut body = self.statement();
+        consume!(self, RightParen, "Expected ')' after parameters.");
+        consume!(self, LeftBrace, "Expected '{{' before function body.");
 
-        if let Some(incr) = increment {
-            body = Statement::Block(vec![body, Statement::Expression(incr)]);
-        }
+        let body = self.with_function_body(|parser| parser.block())?;
 
-        body = Statement::While(WhileStatement {
-            condition: condition.unwrap_or(Expr::Literal(LiteralExpr {
-                value: LiteralExprType::Identifier(Keyword::True),
-            })),
-            body: Box::new(body),
-            in_for_loop: true,
-        });
+        Ok(FunctionStatement {
+            name,
+            params: parameters,
+            is_variadic,
+            body,
+            line: name_line,
+        })
+    }
 
-        if let Some(init) = initializer {
-            body = Statement::Block(vec![init, body]);
-        }
+    fn var_declaration(&mut self, is_const: bool) -> Result<Statement, LoxError> {
+        let token = self.cursor.peek().clone();
+        let line = token.line();
 
-        return body;
-    }
+        match &token {
+            Token::Keyword(k) => match &k.keyword {
+                Keyword::Identifier(name) => {
+                    let n = name.clone();
 
-    fn while_statement(&mut self) -> Statement {
-        consume!(self, LeftParen, "Error: Expected '(' after 'while'.");
+                    self.cursor.advance();
 
-        let condition = self.expression();
+                    let initializer = if match_token!(self, Equal).is_some() {
+                        Some(self.expression()?)
+                    } else {
+                        None
+                    };
 
-        consume!(self, RightParen, "Error: Expected ')' after condition.");
+                    if is_const && initializer.is_none() {
+                        return Err(parse_error!(line, "Constant '{}' must be initialized.", n));
+                    }
 
-        let body = self.statement();
+                    consume!(self, Semicolon, "Missing ';'.");
 
-        return Statement::While(WhileStatement {
-            body: Box::new(body),
+                    Ok(Statement::Var(VarStatement {
+                        name: n,
+                        initializer,
+                        is_const,
+                        line,
+                    }))
+                }
+                _ => Err(parse_error!(
+                    line,
+                    "The name of your variable cannot be a keyword."
+                )),
+            },
+            _ => Err(parse_error!(line, "Provide a name for your variable.")),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, LoxError> {
+        if let Some(label) = self.label_at_cursor()
+            && matches!(self.cursor.peek_next(), Token::Colon(_))
+        {
+            let line = self.cursor.advance().line();
+            self.cursor.advance();
+            return self.labeled_statement(label, line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, For) {
+            let line = token.line();
+            return self.for_statement(line);
+        }
+
+        if match_token!(self, Keyword, Print).is_some() {
+            return self.print_statement();
+        }
+
+        if let Some(token) = match_token!(self, Keyword, Return) {
+            let line = token.line();
+            return self.return_statement(line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, While) {
+            let line = token.line();
+            return self.while_statement(line);
+        }
+
+        if match_token!(self, Keyword, Do).is_some() {
+            return self.do_while_statement();
+        }
+
+        if let Some(token) = match_token!(self, Keyword, If) {
+            let line = token.line();
+            return self.if_statement(line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, Switch) {
+            let line = token.line();
+            return self.switch_statement(line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, Break) {
+            let line = token.line();
+            return self.break_statement(line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, Continue) {
+            let line = token.line();
+            return self.continue_statement(line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, Try) {
+            let line = token.line();
+            return self.try_statement(line);
+        }
+
+        if let Some(token) = match_token!(self, Keyword, Throw) {
+            let line = token.line();
+            return self.throw_statement(line);
+        }
+
+        if !self.at_map_literal() && match_token!(self, LeftBrace).is_some() {
+            return Ok(Statement::Block(self.block()?));
+        }
+
+        self.expression_statement()
+    }
+
+    // A `{` followed by a string or identifier key and a `:` starts a map
+    // literal rather than a block.
+    fn at_map_literal(&self) -> bool {
+        let key = match self.cursor.peek_next() {
+            Token::String(_) => true,
+            Token::Keyword(k) => matches!(k.keyword, Keyword::Identifier(_)),
+            _ => false,
+        };
+
+        (matches!(self.cursor.peek(), Token::LeftBrace(_))
+            && key
+            && matches!(self.cursor.peek_nth(2), Token::Colon(_)))
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, LoxError> {
+        let mut statements = vec![];
+
+        while !peek_token!(self, RightBrace | Eof) {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => self.recover(err),
+            }
+        }
+
+        consume!(self, RightBrace, "Missing '}}'.");
+
+        Ok(statements)
+    }
+
+    fn for_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        consume!(self, LeftParen, "Expect '(' after 'for'.");
+
+        let is_for_in =
+            matches!(self.cursor.peek_nth(2), Token::Keyword(k) if k.keyword == Keyword::In);
+
+        if peek_token!(self, Keyword, Var) && is_for_in {
+            return self.for_in_statement(line);
+        }
+
+        let initializer;
+        if match_token!(self, Semicolon).is_some() {
+            initializer = None;
+        } else if match_token!(self, Keyword, Var).is_some() {
+            initializer = Some(self.var_declaration(false)?);
+        } else {
+            initializer = Some(self.expression_statement()?);
+        }
+
+        let mut condition = None;
+        if !peek_token!(self, Semicolon) {
+            condition = Some(self.expression()?);
+        }
+        consume!(self, Semicolon, "Expect ';' after loop condition.");
+
+        let mut increment = None;
+        if !peek_token!(self, RightParen) {
+            increment = Some(self.expression()?);
+        }
+        consume!(self, RightParen, "Expect ')' after for clauses.");
+
+        let mut body = self.with_loop(|parser| parser.statement())?;
+
+        let has_increment = increment.is_some();
+        if let Some(incr) = increment {
+            body = Statement::Block(vec![body, Statement::Expression(incr)]);
+        }
+
+        body = Statement::While(WhileStatement {
+            condition: condition.unwrap_or(Expr::Literal(LiteralExpr {
+                value: LiteralExprType::Identifier(Keyword::True),
+            })),
+            body: Box::new(body),
+            in_for_loop: has_increment,
+            line,
+        });
+
+        if let Some(init) = initializer {
+            body = Statement::Block(vec![init, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn for_in_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        consume!(self, Keyword, Var, "Expected 'var' in for-in loop.");
+        let name = consume!(self, Keyword, Identifier, "Expected loop variable name.");
+
+        let Token::Keyword(name) = name else {
+            unreachable!()
+        };
+        let variable = name.keyword.lexeme().to_string();
+
+        consume!(self, Keyword, In, "Expected 'in' after loop variable.");
+        let iterable = self.expression()?;
+        consume!(self, RightParen, "Expect ')' after for-in clauses.");
+
+        let body = self.with_loop(|parser| parser.statement())?;
+
+        Ok(Statement::ForIn(ForInStatement {
+            variable,
+            iterable,
+            body: Box::new(body),
+            line,
+        }))
+    }
+
+    fn while_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        consume!(self, LeftParen, "Expected '(' after 'while'.");
+
+        let condition = self.expression()?;
+
+        consume!(self, RightParen, "Expected ')' after condition.");
+
+        let body = self.with_loop(|parser| parser.statement())?;
+
+        Ok(Statement::While(WhileStatement {
+            body: Box::new(body),
             condition,
             in_for_loop: false,
-        });
+            line,
+        }))
+    }
+
+    fn do_while_statement(&mut self) -> Result<Statement, LoxError> {
+        let body = self.with_loop(|parser| parser.statement())?;
+
+        consume!(self, Keyword, While, "Expected 'while' after do body.");
+        consume!(self, LeftParen, "Expected '(' after 'while'.");
+        let condition = self.expression()?;
+        consume!(self, RightParen, "Expected ')' after condition.");
+        consume!(self, Semicolon, "Expected ';' after do-while condition.");
+
+        Ok(Statement::DoWhile(DoWhileStatement {
+            body: Box::new(body),
+            condition,
+        }))
     }
 
-    fn return_statement(&mut self, keyword: Token) -> Statement {
+    fn return_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
         let mut value = None;
 
-        if let Some(token) = self.tokens.get(self.current) {
-            match token {
-                Token::Semicolon(_) => {}
-                _ => {
-                    value = Some(self.expression());
-                }
-            }
+        if !peek_token!(self, Semicolon) {
+            value = Some(self.expression()?);
         }
 
-        consume!(self, Semicolon, "Error: Missing ';'.");
+        consume!(self, Semicolon, "Missing ';'.");
 
-        return Statement::Return(ReturnStatement { keyword, value });
+        Ok(Statement::Return(ReturnStatement { line, value }))
     }
 
-    fn if_statement(&mut self) -> Statement {
-        consume!(self, LeftParen, "Error: Expected '(' after 'if'.");
+    fn if_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        consume!(self, LeftParen, "Expected '(' after 'if'.");
 
-        let condition = self.expression();
+        let condition = self.expression()?;
 
-        consume!(self, RightParen, "Error: Expected ')' after if condition.");
+        consume!(self, RightParen, "Expected ')' after if condition.");
 
-        let then_branch = self.statement();
-        let else_branch = if let Some(_) = match_token!(self, Keyword, Else) {
-            Some(self.statement())
+        let then_branch = self.statement()?;
+        let else_branch = if match_token!(self, Keyword, Else).is_some() {
+            Some(self.statement()?)
         } else {
             None
         };
 
-        return Statement::If(IfStatement {
+        Ok(Statement::If(IfStatement {
             condition,
             then_branch: Box::new(then_branch),
             else_branch: else_branch.map(Box::new),
-        });
+            line,
+        }))
+    }
+
+    fn switch_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        consume!(self, LeftParen, "Expected '(' after 'switch'.");
+        let discriminant = self.expression()?;
+        consume!(self, RightParen, "Expected ')' after switch value.");
+        consume!(self, LeftBrace, "Expected '{{' before switch body.");
+
+        let (cases, default) = self.with_switch(|parser| parser.switch_clauses(line))?;
+
+        consume!(self, RightBrace, "Expected '}}' after switch body.");
+
+        Ok(Statement::Switch(SwitchStatement {
+            discriminant,
+            cases,
+            default,
+        }))
+    }
+
+    fn switch_clauses(&mut self, line: usize) -> Result<SwitchClauses, LoxError> {
+        let mut cases = vec![];
+        let mut default = None;
+
+        while !peek_token!(self, RightBrace | Eof) {
+            if default.is_some() {
+                return Err(parse_error!(
+                    line,
+                    "'default' must be the last switch clause."
+                ));
+            }
+
+            if match_token!(self, Keyword, Case).is_some() {
+                let value = self.expression()?;
+                consume!(self, Colon, "Expected ':' after case value.");
+                cases.push((value, self.switch_clause_body()?));
+            } else if match_token!(self, Keyword, Default).is_some() {
+                consume!(self, Colon, "Expected ':' after 'default'.");
+                default = Some(self.switch_clause_body()?);
+            } else {
+                return Err(parse_error!(
+                    line,
+                    "Expected 'case' or 'default' in switch body."
+                ));
+            }
+        }
+
+        Ok((cases, default))
     }
 
-    fn print_statement(&mut self) -> Statement {
-        let value = self.expression();
+    fn switch_clause_body(&mut self) -> Result<Vec<Statement>, LoxError> {
+        let mut statements = vec![];
 
-        consume!(self, Semicolon, "Error: Missing ';'.");
+        while !peek_token!(self, Keyword, Case | Default) && !peek_token!(self, RightBrace | Eof) {
+            statements.push(self.declaration()?);
+        }
 
-        return Statement::Print(value);
+        Ok(statements)
     }
 
-    fn break_statement(&mut self) -> Statement {
-        consume!(self, Semicolon, "Error: Missing ';'.");
+    fn print_statement(&mut self) -> Result<Statement, LoxError> {
+        let value = self.expression()?;
 
-        return Statement::Break;
+        consume!(self, Semicolon, "Missing ';'.");
+
+        Ok(Statement::Print(value))
     }
 
-    fn continue_statement(&mut self) -> Statement {
-        consume!(self, Semicolon, "Error: Missing ';'.");
+    fn labeled_statement(&mut self, label: String, line: usize) -> Result<Statement, LoxError> {
+        if !peek_token!(self, Keyword, For | While | Do) {
+            return Err(parse_error!(
+                line,
+                "Expected a loop after label '{}'.",
+                label
+            ));
+        }
 
-        return Statement::Continue;
+        if self.labels.contains(&label) {
+            return Err(parse_error!(line, "Label '{}' is already in use.", label));
+        }
+
+        let body = self.with_label(label.clone(), |parser| parser.statement())?;
+
+        Ok(Statement::Labeled(LabeledStatement {
+            label,
+            body: Box::new(body),
+        }))
+    }
+
+    fn label_at_cursor(&self) -> Option<String> {
+        match self.cursor.peek() {
+            Token::Keyword(k) => match &k.keyword {
+                Keyword::Identifier(name) => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Parses the optional label after `break` or `continue`.
+    fn jump_label(&mut self, line: usize) -> Result<Option<String>, LoxError> {
+        let Some(label) = self.label_at_cursor() else {
+            return Ok(None);
+        };
+
+        if !self.labels.contains(&label) {
+            return Err(parse_error!(line, "Undefined label '{}'.", label));
+        }
+
+        self.cursor.advance();
+
+        Ok(Some(label))
+    }
+
+    fn break_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        if self.loop_depth == 0 && self.switch_depth == 0 {
+            return Err(parse_error!(
+                line,
+                "Cannot use 'break' outside of a loop or switch."
+            ));
+        }
+
+        let label = self.jump_label(line)?;
+        consume!(self, Semicolon, "Missing ';'.");
+
+        Ok(Statement::Break(JumpStatement { line, label }))
+    }
+
+    fn continue_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        if self.loop_depth == 0 {
+            return Err(parse_error!(
+                line,
+                "Cannot use 'continue' outside of a loop."
+            ));
+        }
+
+        let label = self.jump_label(line)?;
+        consume!(self, Semicolon, "Missing ';'.");
+
+        Ok(Statement::Continue(JumpStatement { line, label }))
+    }
+
+    fn try_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        consume!(self, LeftBrace, "Expected '{{' after 'try'.");
+        let body = Statement::Block(self.block()?);
+
+        let mut catch_var = None;
+        let mut catch_body = None;
+
+        if match_token!(self, Keyword, Catch).is_some() {
+            if match_token!(self, LeftParen).is_some() {
+                let name = consume!(self, Keyword, Identifier, "Expected catch variable name.");
+
+                let Token::Keyword(name) = name else {
+                    unreachable!()
+                };
+                catch_var = Some(name.keyword.lexeme().to_string());
+
+                consume!(self, RightParen, "Expected ')' after catch variable.");
+            }
+
+            consume!(self, LeftBrace, "Expected '{{' after 'catch'.");
+            catch_body = Some(Box::new(Statement::Block(self.block()?)));
+        }
+
+        let mut finally_body = None;
+
+        if match_token!(self, Keyword, Finally).is_some() {
+            consume!(self, LeftBrace, "Expected '{{' after 'finally'.");
+            finally_body = Some(Box::new(Statement::Block(self.block()?)));
+        }
+
+        if catch_body.is_none() && finally_body.is_none() {
+            return Err(parse_error!(
+                line,
+                "Expected 'catch' or 'finally' after try block."
+            ));
+        }
+
+        Ok(Statement::Try(TryStatement {
+            body: Box::new(body),
+            catch_var,
+            catch_body,
+            finally_body,
+        }))
     }
 
-    fn expression_statement(&mut self) -> Statement {
-        let expr = self.expression();
+    fn throw_statement(&mut self, line: usize) -> Result<Statement, LoxError> {
+        let value = self.expression()?;
 
-        consume!(self, Semicolon | Eof, "Error: Missing ';'.");
+        consume!(self, Semicolon, "Missing ';'.");
 
-        return Statement::Expression(expr);
+        Ok(Statement::Throw(ThrowStatement { value, line }))
     }
 
-    pub fn parse(&mut self) -> Vec<Statement> {
+    fn expression_statement(&mut self) -> Result<Statement, LoxError> {
+        let expr = self.expression()?;
+
+        consume!(self, Semicolon, "Missing ';'.");
+
+        Ok(Statement::Expression(expr))
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.expression()?;
+
+        match_token!(self, Semicolon);
+
+        if !self.cursor.is_at_end() {
+            return Err(parse_error!(
+                self.cursor.peek().line(),
+                "Expected end of expression."
+            ));
+        }
+
+        Resolver::new().resolve_expression(&mut expr)?;
+
+        Ok(expr)
+    }
+
+    /// Parses the whole program and resolves its variables, so the result can be interpreted
+    /// as many times as needed.
+    pub fn parse(&mut self) -> Result<Vec<Statement>, CompileErrors> {
         let mut statements = vec![];
 
-        while self.current < self.tokens.len() {
-            statements.push(self.declaration());
+        while !self.cursor.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.recover(err);
+
+                    // A stray '}' has no block to close it at the top level.
+                    if peek_token!(self, RightBrace) {
+                        self.cursor.advance();
+                    }
+                }
+            }
+        }
+
+        if self.errors.is_empty()
+            && let Err(LoxError::Compile(err)) = Resolver::new().resolve(&mut statements)
+        {
+            self.errors.push(err);
+        }
+
+        if !self.errors.is_empty() {
+            return Err(CompileErrors(std::mem::take(&mut self.errors)));
+        }
+
+        Ok(statements)
+    }
+
+    // The `with_*` helpers run `parse` inside a nested context and restore the
+    // enclosing one afterwards, whether parsing succeeded or not.
+    fn with_loop<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        self.loop_depth += 1;
+        let result = parse(self);
+        self.loop_depth -= 1;
+
+        result
+    }
+
+    fn with_switch<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        self.switch_depth += 1;
+        let result = parse(self);
+        self.switch_depth -= 1;
+
+        result
+    }
+
+    fn with_label<T>(
+        &mut self,
+        label: String,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        self.labels.push(label);
+        let result = parse(self);
+        self.labels.pop();
+
+        result
+    }
+
+    fn with_class<T>(
+        &mut self,
+        kind: ClassKind,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        self.classes.push(kind);
+        let result = parse(self);
+        self.classes.pop();
+
+        result
+    }
+
+    // Loops, switches and labels don't reach into a function body.
+    fn with_function_body<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let switch_depth = std::mem::take(&mut self.switch_depth);
+        let labels = std::mem::take(&mut self.labels);
+
+        let result = parse(self);
+
+        self.loop_depth = loop_depth;
+        self.switch_depth = switch_depth;
+        self.labels = labels;
+
+        result
+    }
+
+    fn recover(&mut self, err: LoxError) {
+        match err {
+            LoxError::Compile(err) => self.errors.push(err),
+            LoxError::Runtime { .. } | LoxError::Thrown { .. } => unreachable!(),
         }
 
-        return statements;
+        self.synchronize();
+    }
+
+    // Discards tokens until the start of the next statement so that parsing can
+    // resume after an error and report the ones that follow it. Nested blocks are
+    // skipped whole, and the '}' closing the enclosing block is left for it to consume.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        while !self.cursor.is_at_end() {
+            if depth == 0 && peek_token!(self, RightBrace) {
+                return;
+            }
+
+            match self.cursor.advance() {
+                Token::Semicolon(_) if depth == 0 => return,
+                Token::LeftBrace(_) => depth += 1,
+                Token::RightBrace(_) => depth -= 1,
+                _ => {}
+            }
+
+            if depth > 0 {
+                continue;
+            }
+
+            let at_statement_start = self.cursor.check(|token| match token {
+                Token::Keyword(k) => matches!(
+                    k.keyword,
+                    Keyword::Class
+                        | Keyword::Fun
+                        | Keyword::Var
+                        | Keyword::Const
+                        | Keyword::For
+                        | Keyword::Do
+                        | Keyword::Switch
+                        | Keyword::If
+                        | Keyword::While
+                        | Keyword::Print
+                        | Keyword::Return
+                        | Keyword::Try
+                        | Keyword::Throw
+                        | Keyword::Break
+                        | Keyword::Continue
+                ),
+                _ => false,
+            });
+
+            if at_statement_start {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+
+    fn ast(source: &str) -> String {
+        let statements = Parser::new(scan(source).unwrap()).parse().unwrap();
+
+        statements
+            .iter()
+            .map(|stmt| stmt.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn parse_errors(source: &str) -> Vec<String> {
+        match Parser::new(scan(source).unwrap()).parse() {
+            Ok(_) => vec![],
+            Err(errors) => errors.0.iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn prints_binary_with_precedence() {
+        assert_eq!(ast("1 + 2 * 3;"), "(; (+ 1 (* 2 3)))");
+    }
+
+    #[test]
+    fn prints_grouping() {
+        assert_eq!(ast("(1 + 2) * 3;"), "(; (* (group (+ 1 2)) 3))");
+    }
+
+    #[test]
+    fn prints_literals() {
+        assert_eq!(
+            ast("nil; true; \"s\"; 1.5;"),
+            "(; nil)\n(; true)\n(; \"s\")\n(; 1.5)"
+        );
+    }
+
+    #[test]
+    fn prints_unary() {
+        assert_eq!(ast("-a; !a; ~a;"), "(; (- a))\n(; (! a))\n(; (~ a))");
+    }
+
+    #[test]
+    fn prints_logical() {
+        assert_eq!(ast("a and b or c;"), "(; (or (and a b) c))");
+    }
+
+    #[test]
+    fn prints_ternary() {
+        assert_eq!(ast("a ? b : c;"), "(; (?: a b c))");
+    }
+
+    #[test]
+    fn prints_assignment() {
+        assert_eq!(ast("x = 1;"), "(; (= x 1))");
+    }
+
+    #[test]
+    fn prints_call() {
+        assert_eq!(ast("f(1, 2);"), "(; (call f 1 2))");
+    }
+
+    #[test]
+    fn prints_get_and_set() {
+        assert_eq!(ast("a.b; a.b = 2;"), "(; (. a b))\n(; (.= a b 2))");
+    }
+
+    #[test]
+    fn prints_array_and_index() {
+        assert_eq!(
+            ast("[1, 2]; a[0]; a[0] = 1;"),
+            "(; (array 1 2))\n(; ([] a 0))\n(; ([]= a 0 1))"
+        );
+    }
+
+    #[test]
+    fn prints_map() {
+        assert_eq!(
+            ast("var m = {\"k\": 1, j: 2};"),
+            "(var m (map (\"k\" 1) (\"j\" 2)))"
+        );
+    }
+
+    #[test]
+    fn prints_function_expression() {
+        assert_eq!(
+            ast("var g = fun (x) { return x; };"),
+            "(var g (fun (x) (return x)))"
+        );
+    }
+
+    #[test]
+    fn prints_print_and_var() {
+        assert_eq!(
+            ast("print 1; var v; var w = 2;"),
+            "(print 1)\n(var v)\n(var w 2)"
+        );
+    }
+
+    #[test]
+    fn prints_block() {
+        assert_eq!(
+            ast("{ var w = 1; print w; }"),
+            "(block (var w 1) (print w))"
+        );
+    }
+
+    #[test]
+    fn prints_if_with_and_without_else() {
+        assert_eq!(
+            ast("if (a) print 1; else print 2; if (b) print 3;"),
+            "(if a (print 1) (print 2))\n(if b (print 3))"
+        );
+    }
+
+    #[test]
+    fn prints_while_with_break() {
+        assert_eq!(ast("while (a) break;"), "(while a (break))");
+    }
+
+    #[test]
+    fn prints_desugared_for() {
+        assert_eq!(
+            ast("for (var i = 0; i < 2; i = i + 1) continue;"),
+            "(block (var i 0) (while (< i 2) (block (continue) (; (= i (+ i 1))))))"
+        );
+    }
+
+    #[test]
+    fn prints_function_declaration() {
+        assert_eq!(ast("fun h(a, b) { return; }"), "(fun h (a b) (return))");
+    }
+
+    #[test]
+    fn prints_class_with_super_and_this() {
+        assert_eq!(
+            ast("class B < A { m() { return super.m(); } n() { return this; } }"),
+            "(class B < A (fun m () (return (call (super m)))) (fun n () (return this)))"
+        );
+    }
+
+    #[test]
+    fn prints_return_value() {
+        assert_eq!(ast("fun f() { return 1; }"), "(fun f () (return 1))");
+    }
+
+    #[test]
+    fn prints_update() {
+        assert_eq!(
+            ast("i++; ++i; a.b--; --a[0];"),
+            "(; (post++ i))\n(; (pre++ i))\n(; (post-- (. a b)))\n(; (pre-- ([] a 0)))"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_update_target() {
+        assert_eq!(
+            parse_errors("++++i;"),
+            vec!["[line 1] Error: Invalid increment target."]
+        );
+    }
+
+    #[test]
+    fn restores_loop_depth_after_an_error_in_the_body() {
+        assert_eq!(
+            parse_errors("while (true) print 1 +;\nbreak;"),
+            vec![
+                "[line 1] Error: Unexpected token Semicolon ';' encountered.",
+                "[line 2] Error: Cannot use 'break' outside of a loop or switch.",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_one_error_inside_a_function_body() {
+        assert_eq!(
+            parse_errors("fun f() {\n var x = 1 +;\n print x;\n}\nprint 2;"),
+            vec!["[line 2] Error: Unexpected token Semicolon ';' encountered."]
+        );
+    }
+
+    #[test]
+    fn keeps_parsing_after_an_error_in_a_block() {
+        assert_eq!(
+            parse_errors("{\n print 1 +;\n print 2 *;\n}"),
+            vec![
+                "[line 2] Error: Unexpected token Semicolon ';' encountered.",
+                "[line 3] Error: Unexpected token Semicolon ';' encountered.",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_the_body_of_a_statement_with_an_error() {
+        assert_eq!(
+            parse_errors("if (1 +) { break; }\nprint 1;"),
+            vec!["[line 1] Error: Unexpected token RightParen ')' encountered."]
+        );
+    }
+
+    #[test]
+    fn desugars_compound_assignment() {
+        assert_eq!(ast("x += 1;"), "(; (= x (+ x 1)))");
+    }
+
+    #[test]
+    fn rejects_compound_assignment_to_a_call() {
+        assert_eq!(
+            parse_errors("f() += 1;"),
+            vec!["[line 1] Error: Invalid assignment target."]
+        );
+    }
+
+    #[test]
+    fn parses_exponentiation_right_associatively() {
+        assert_eq!(ast("2 ** 3 ** 2;"), "(; (** 2 (** 3 2)))");
+    }
+
+    fn names(count: usize) -> String {
+        (0..count)
+            .map(|i| format!("p{}", i))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    #[test]
+    fn limits_parameters_and_arguments_to_255() {
+        assert!(parse_errors(&format!("fun f({}) {{}}", names(255))).is_empty());
+        assert!(parse_errors(&format!("f({});", names(255))).is_empty());
+
+        assert_eq!(
+            parse_errors(&format!("fun f({}) {{}}", names(256))),
+            vec!["[line 1] Error: Can't have more than 255 parameters."]
+        );
+        assert_eq!(
+            parse_errors(&format!("f({});", names(256))),
+            vec!["[line 1] Error: Can't have more than 255 arguments."]
+        );
+    }
+
+    #[test]
+    fn reports_missing_semicolon() {
+        assert_eq!(
+            parse_errors("print 1"),
+            vec!["[line 1] Error: Missing ';'."]
+        );
+    }
+
+    #[test]
+    fn prints_typeof() {
+        assert_eq!(ast("typeof a;"), "(; (typeof a))");
+    }
+
+    #[test]
+    fn rejects_postfix_typeof() {
+        assert_eq!(parse_errors("1 typeof 2;").len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_assignment_target() {
+        assert_eq!(
+            parse_errors("1 = 2;"),
+            vec!["[line 1] Error: Invalid assignment target."]
+        );
+    }
+
+    #[test]
+    fn try_needs_catch_or_finally() {
+        assert_eq!(ast("try {} finally {}"), "(try (block) (finally (block)))");
+        assert_eq!(
+            parse_errors("try {}"),
+            vec!["[line 1] Error: Expected 'catch' or 'finally' after try block."]
+        );
+    }
+
+    #[test]
+    fn reports_missing_expression_at_end_of_input() {
+        assert_eq!(
+            parse_errors("print 1 +"),
+            vec!["[line 1] Error: Expect expression."]
+        );
+        assert_eq!(
+            parse_errors("var a = 1;\nprint a\nprint 2;"),
+            vec!["[line 2] Error: Missing ';'."]
+        );
+    }
+
+    #[test]
+    fn requires_semicolon_after_expression_statement() {
+        assert_eq!(parse_errors("1 + 2"), vec!["[line 1] Error: Missing ';'."]);
+    }
+
+    #[test]
+    fn rejects_super_outside_a_subclass() {
+        assert_eq!(
+            parse_errors("fun f() { return super.x; }"),
+            vec!["[line 1] Error: Can't use 'super' outside of a class."]
+        );
+        assert_eq!(
+            parse_errors("class A { m() { return super.m(); } }"),
+            vec!["[line 1] Error: Can't use 'super' in a class with no superclass."]
+        );
+    }
+
+    #[test]
+    fn requires_the_rest_parameter_last() {
+        assert_eq!(
+            parse_errors("fun g(a, ...b, c) {}"),
+            vec!["[line 1] Error: Rest parameter must be the last parameter."]
+        );
+    }
+
+    #[test]
+    fn rejects_required_parameters_after_defaults() {
+        assert_eq!(
+            parse_errors("fun g(a = 1, b) {}"),
+            vec![
+                "[line 1] Error: Parameter 'b' without a default cannot follow one with a default."
+            ]
+        );
+    }
+
+    #[test]
+    fn requires_constants_to_be_initialized() {
+        assert_eq!(
+            parse_errors("const x;"),
+            vec!["[line 1] Error: Constant 'x' must be initialized."]
+        );
     }
 }
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use crate::{
+    LoxError,
+    expression::Expr,
+    parse_error,
+    statement::{FunctionStatement, Statement},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+pub struct Resolver {
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![],
+            current_function: FunctionType::None,
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &mut [Statement]) -> Result<(), LoxError> {
+        for stmt in statements {
+            self.resolve_statement(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn resolve_expression(&mut self, expr: &mut Expr) -> Result<(), LoxError> {
+        match expr {
+            Expr::Array(array_expr) => {
+                for element in &mut array_expr.elements {
+                    self.resolve_expression(element)?;
+                }
+            }
+            Expr::Assign(assign_expr) => {
+                self.resolve_expression(&mut assign_expr.value)?;
+                assign_expr.depth = self.depth_of(&assign_expr.name);
+            }
+            Expr::Binary(binary_expr) => {
+                self.resolve_expression(&mut binary_expr.left)?;
+                self.resolve_expression(&mut binary_expr.right)?;
+            }
+            Expr::Call(call_expr) => {
+                self.resolve_expression(&mut call_expr.callee)?;
+
+                for argument in &mut call_expr.arguments {
+                    self.resolve_expression(argument)?;
+                }
+            }
+            Expr::Function(function_expr) => {
+                self.resolve_function(&mut function_expr.function, FunctionType::Function)?;
+            }
+            Expr::Get(get_expr) => {
+                self.resolve_expression(&mut get_expr.object)?;
+            }
+            Expr::Grouping(grouping_expr) => {
+                self.resolve_expression(&mut grouping_expr.expression)?;
+            }
+            Expr::Index(index_expr) => {
+                self.resolve_expression(&mut index_expr.object)?;
+                self.resolve_expression(&mut index_expr.index)?;
+            }
+            Expr::IndexSet(index_set_expr) => {
+                self.resolve_expression(&mut index_set_expr.value)?;
+                self.resolve_expression(&mut index_set_expr.object)?;
+                self.resolve_expression(&mut index_set_expr.index)?;
+            }
+            Expr::Literal(_) => {}
+            Expr::Logical(logical_expr) => {
+                self.resolve_expression(&mut logical_expr.left)?;
+                self.resolve_expression(&mut logical_expr.right)?;
+            }
+            Expr::Map(map_expr) => {
+                for (key, value) in &mut map_expr.pairs {
+                    self.resolve_expression(key)?;
+                    self.resolve_expression(value)?;
+                }
+            }
+            Expr::NilCoalesce(nil_coalesce_expr) => {
+                self.resolve_expression(&mut nil_coalesce_expr.left)?;
+                self.resolve_expression(&mut nil_coalesce_expr.right)?;
+            }
+            Expr::OptionalGet(optional_get_expr) => {
+                self.resolve_expression(&mut optional_get_expr.object)?;
+            }
+            Expr::Set(set_expr) => {
+                self.resolve_expression(&mut set_expr.value)?;
+                self.resolve_expression(&mut set_expr.object)?;
+            }
+            Expr::Spread(spread_expr) => {
+                self.resolve_expression(&mut spread_expr.expression)?;
+            }
+            Expr::Super(_) => {}
+            Expr::Ternary(ternary_expr) => {
+                self.resolve_expression(&mut ternary_expr.condition)?;
+                self.resolve_expression(&mut ternary_expr.trueish)?;
+                self.resolve_expression(&mut ternary_expr.falseish)?;
+            }
+            Expr::This(_) => {}
+            Expr::Unary(unary_expr) => {
+                self.resolve_expression(&mut unary_expr.right)?;
+            }
+            Expr::Update(update_expr) => {
+                self.resolve_expression(&mut update_expr.target)?;
+            }
+            Expr::Variable(variable_expr) => {
+                let in_own_initializer = self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.get(&variable_expr.name) == Some(&false));
+
+                if in_own_initializer {
+                    return Err(parse_error!(
+                        variable_expr.line,
+                        "Can't read local variable '{}' in its own initializer.",
+                        variable_expr.name
+                    ));
+                }
+
+                variable_expr.depth = self.depth_of(&variable_expr.name);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_statement(&mut self, stmt: &mut Statement) -> Result<(), LoxError> {
+        match stmt {
+            Statement::Expression(expr) | Statement::Print(expr) => {
+                self.resolve_expression(expr)?;
+            }
+            Statement::Var(vs) => {
+                self.declare(&vs.name, vs.line)?;
+
+                if let Some(initializer) = &mut vs.initializer {
+                    self.resolve_expression(initializer)?;
+                }
+
+                self.define(&vs.name);
+            }
+            Statement::Block(block) => {
+                self.scopes.push(HashMap::new());
+                self.resolve(block)?;
+                self.scopes.pop();
+            }
+            Statement::If(is) => {
+                self.resolve_expression(&mut is.condition)?;
+                self.resolve_statement(&mut is.then_branch)?;
+
+                if let Some(else_branch) = &mut is.else_branch {
+                    self.resolve_statement(else_branch)?;
+                }
+            }
+            Statement::Switch(ss) => {
+                self.resolve_expression(&mut ss.discriminant)?;
+
+                for (value, body) in &mut ss.cases {
+                    self.resolve_expression(value)?;
+
+                    self.scopes.push(HashMap::new());
+                    self.resolve(body)?;
+                    self.scopes.pop();
+                }
+
+                if let Some(default) = &mut ss.default {
+                    self.scopes.push(HashMap::new());
+                    self.resolve(default)?;
+                    self.scopes.pop();
+                }
+            }
+            Statement::While(ws) => {
+                self.resolve_expression(&mut ws.condition)?;
+                self.resolve_statement(&mut ws.body)?;
+            }
+            Statement::DoWhile(ds) => {
+                self.resolve_statement(&mut ds.body)?;
+                self.resolve_expression(&mut ds.condition)?;
+            }
+            Statement::ForIn(fs) => {
+                self.resolve_expression(&mut fs.iterable)?;
+
+                self.scopes.push(HashMap::new());
+                self.define(&fs.variable);
+                self.resolve_statement(&mut fs.body)?;
+                self.scopes.pop();
+            }
+            Statement::Function(fs) => {
+                self.declare(&fs.name, fs.line)?;
+                self.define(&fs.name);
+
+                self.resolve_function(fs, FunctionType::Function)?;
+            }
+            Statement::Class(cs) => {
+                self.declare(&cs.name, cs.line)?;
+                self.define(&cs.name);
+
+                if let Some(superclass) = &mut cs.superclass {
+                    superclass.depth = self.depth_of(&superclass.name);
+
+                    self.scopes.push(HashMap::new());
+                    self.define("super");
+                }
+
+                self.scopes.push(HashMap::new());
+                self.define("this");
+
+                for method in &mut cs.methods {
+                    let kind = if method.name == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+
+                    self.resolve_function(method, kind)?;
+                }
+
+                self.scopes.pop();
+
+                if cs.superclass.is_some() {
+                    self.scopes.pop();
+                }
+            }
+            Statement::Labeled(ls) => {
+                self.resolve_statement(&mut ls.body)?;
+            }
+            Statement::Break(_) | Statement::Continue(_) => {}
+            Statement::Return(rs) => {
+                if self.current_function == FunctionType::None {
+                    return Err(parse_error!(rs.line, "Can't return from top-level code."));
+                }
+
+                if let Some(value) = &mut rs.value {
+                    if self.current_function == FunctionType::Initializer {
+                        return Err(parse_error!(
+                            rs.line,
+                            "Can't return a value from an initializer."
+                        ));
+                    }
+
+                    self.resolve_expression(value)?;
+                }
+            }
+            Statement::Try(ts) => {
+                self.resolve_statement(&mut ts.body)?;
+
+                if let Some(catch_body) = &mut ts.catch_body {
+                    self.scopes.push(HashMap::new());
+
+                    if let Some(catch_var) = &ts.catch_var {
+                        self.define(catch_var);
+                    }
+
+                    self.resolve_statement(catch_body)?;
+                    self.scopes.pop();
+                }
+
+                if let Some(finally_body) = &mut ts.finally_body {
+                    self.resolve_statement(finally_body)?;
+                }
+            }
+            Statement::Throw(ts) => {
+                self.resolve_expression(&mut ts.value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_function(
+        &mut self,
+        fs: &mut FunctionStatement,
+        kind: FunctionType,
+    ) -> Result<(), LoxError> {
+        let enclosing_function = std::mem::replace(&mut self.current_function, kind);
+        self.scopes.push(HashMap::new());
+
+        for (param, line, default) in &mut fs.params {
+            if let Some(default) = default {
+                self.resolve_expression(default)?;
+            }
+
+            self.declare(param, *line)?;
+            self.define(param);
+        }
+
+        self.resolve(&mut fs.body)?;
+        self.scopes.pop();
+        self.current_function = enclosing_function;
+
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &str, line: usize) -> Result<(), LoxError> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Ok(());
+        };
+
+        if scope.contains_key(name) {
+            return Err(parse_error!(
+                line,
+                "Already a variable named '{}' in this scope.",
+                name
+            ));
+        }
+
+        scope.insert(name.to_string(), false);
+
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+
+    fn depth_of(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, scan};
+
+    fn resolve_error(source: &str) -> Option<String> {
+        Parser::new(scan(source).unwrap())
+            .parse()
+            .err()
+            .map(|errors| errors.to_string().trim_end().to_string())
+    }
+
+    #[test]
+    fn rejects_return_from_top_level_code() {
+        assert_eq!(
+            resolve_error("return 1;\nprint 2;").as_deref(),
+            Some("[line 1] Error: Can't return from top-level code.")
+        );
+        assert!(resolve_error("{ return; }").is_some());
+    }
+
+    #[test]
+    fn allows_return_inside_functions_and_methods() {
+        assert!(resolve_error("fun f() { return 1; }").is_none());
+        assert!(resolve_error("var f = fun () { return 1; };").is_none());
+        assert!(resolve_error("class A { m() { return 1; } }").is_none());
+    }
+
+    #[test]
+    fn rejects_returning_a_value_from_an_initializer() {
+        assert_eq!(
+            resolve_error("class A {\n  init() { return 1; }\n}").as_deref(),
+            Some("[line 2] Error: Can't return a value from an initializer.")
+        );
+        assert!(resolve_error("class A { init() { return; } }").is_none());
+        assert!(resolve_error("class A { init() { fun f() { return 1; } } }").is_none());
+    }
+}
@@ -1,8 +1,8 @@
-use std::{cell::RefCell, iter::Peekable, rc::Rc, str::Chars};
+use std::{iter::Peekable, str::Chars};
 
 use crate::{
     CompileError, error,
-    lox_type::LoxNumber,
+    lox_type::{LoxInteger, LoxNumber, LoxString},
     token::{
         Keyword, Token, TokenValue, TokenValueEof, TokenValueKeyword, TokenValueNumber,
         TokenValueString,
@@ -11,35 +11,51 @@ use crate::{
 
 macro_rules! token_n {
     ($self:expr, $variant:ident) => {
-        Token::$variant($self.get_token_value())
+        Token::$variant(Box::new($self.get_token_value()))
     };
 }
 
 pub struct Scanner<'a> {
-    source: &'a String,
+    source: &'a str,
     chars: Peekable<Chars<'a>>,
 
     at_the_end: bool,
-    pub errors: Rc<RefCell<Vec<CompileError>>>,
+    errors: Vec<CompileError>,
 
     start: usize,
     current: usize,
     line: usize,
+    start_col: usize,
+    col: usize,
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(source: &'a String) -> Self {
+    pub fn new(source: &'a str) -> Self {
+        let bom_len = if source.starts_with('\u{FEFF}') {
+            '\u{FEFF}'.len_utf8()
+        } else {
+            0
+        };
+
         Self {
-            source: source,
-            chars: source.chars().peekable(),
-            at_the_end: source.is_empty(),
-            errors: Rc::new(RefCell::new(vec![])),
-            start: 0,
-            current: 0,
+            source,
+            chars: source[bom_len..].chars().peekable(),
+            at_the_end: source.len() == bom_len,
+            errors: vec![],
+            start: bom_len,
+            current: bom_len,
             line: 1,
+            start_col: 1,
+            col: 1,
         }
     }
 
+    pub fn finish(mut self) -> (Vec<Token>, Vec<CompileError>) {
+        let tokens = self.by_ref().collect::<Vec<Token>>();
+
+        (tokens, self.errors)
+    }
+
     fn is_at_end(&mut self) -> bool {
         let at_the_end = self.current >= self.source.len();
 
@@ -47,67 +63,218 @@ impl<'a> Scanner<'a> {
             self.at_the_end = true;
         }
 
-        return at_the_end;
+        at_the_end
     }
 
     fn is_alpha(&self, c: char) -> bool {
-        return (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_';
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alphanumeric(&self, c: char) -> bool {
-        return self.is_alpha(c) || c.is_digit(10);
+        self.is_alpha(c) || c.is_ascii_digit()
     }
 
     fn peek(&mut self) -> char {
-        return *self.chars.peek().unwrap_or(&'\0');
+        *self.chars.peek().unwrap_or(&'\0')
     }
 
     fn peek_next(&mut self) -> char {
         let mut it = self.source[self.current..].chars();
         it.next();
 
-        return it.next().unwrap_or('\0');
+        it.next().unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
-        self.current += 1;
-        return self.chars.next().unwrap();
+        let c = self.chars.next().unwrap();
+        self.current += c.len_utf8();
+
+        if c == '\n' {
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+
+        c
     }
 
     fn number(&mut self) -> Option<Token> {
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
             let _ = self.advance();
         }
 
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             let _ = self.advance();
 
-            while self.peek().is_digit(10) {
+            while self.peek().is_ascii_digit() || self.peek() == '_' {
                 let _ = self.advance();
             }
         }
 
-        return Some(Token::Number(TokenValueNumber {
-            lexeme: self.source[self.start..self.current].to_string(),
+        if self.peek() == 'e' || self.peek() == 'E' {
+            let _ = self.advance();
+
+            if self.peek() == '+' || self.peek() == '-' {
+                let _ = self.advance();
+            }
+
+            if !self.peek().is_ascii_digit() {
+                self.errors.push(error(
+                    self.line,
+                    self.start_col,
+                    &format!(
+                        "Missing exponent digits in number literal '{}'.",
+                        &self.source[self.start..self.current]
+                    ),
+                ));
+
+                return None;
+            }
+
+            while self.peek().is_ascii_digit() || self.peek() == '_' {
+                let _ = self.advance();
+            }
+        }
+
+        let lexeme = &self.source[self.start..self.current];
+
+        if self.has_misplaced_underscore(lexeme, |c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let digits = lexeme.replace('_', "");
+
+        let value = match digits.parse::<LoxNumber>() {
+            Ok(value) if value.is_finite() => value,
+            _ => {
+                self.errors.push(error(
+                    self.line,
+                    self.start_col,
+                    &format!("Number literal '{}' is out of range.", lexeme),
+                ));
+
+                return None;
+            }
+        };
+
+        // Literals without a fraction or exponent become integers when they fit in an i64.
+        let integer = if lexeme.contains(['.', 'e', 'E']) {
+            None
+        } else {
+            digits.parse::<LoxInteger>().ok()
+        };
+
+        Some(Token::Number(Box::new(TokenValueNumber {
+            lexeme: lexeme.to_string(),
             line: self.line,
-            value: self.source[self.start..self.current]
-                .parse::<LoxNumber>()
-                .unwrap(),
-        }));
+            col: self.start_col,
+            value,
+            integer,
+        })))
+    }
+
+    fn has_misplaced_underscore(&mut self, lexeme: &str, is_digit: fn(char) -> bool) -> bool {
+        let chars = lexeme.chars().collect::<Vec<char>>();
+
+        let misplaced = chars.iter().enumerate().any(|(i, c)| {
+            *c == '_'
+                && !(i > 0
+                    && is_digit(chars[i - 1])
+                    && chars.get(i + 1).is_some_and(|next| is_digit(*next)))
+        });
+
+        if misplaced {
+            self.errors.push(error(
+                self.line,
+                self.start_col,
+                &format!(
+                    "Underscores in number literal '{}' must be between digits.",
+                    lexeme
+                ),
+            ));
+        }
+
+        misplaced
+    }
+
+    fn hex_number(&mut self) -> Option<Token> {
+        let _ = self.advance();
+
+        while self.peek().is_ascii_hexdigit() || self.peek() == '_' {
+            let _ = self.advance();
+        }
+
+        let lexeme = &self.source[self.start..self.current];
+
+        if self.has_misplaced_underscore(lexeme, |c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let digits = lexeme[2..].replace('_', "");
+
+        let integer = LoxInteger::from_str_radix(&digits, 16).ok();
+
+        let Ok(value) = u64::from_str_radix(&digits, 16) else {
+            self.errors.push(error(
+                self.line,
+                self.start_col,
+                &format!("Invalid hexadecimal literal '{}'.", lexeme),
+            ));
+
+            return None;
+        };
+
+        Some(Token::Number(Box::new(TokenValueNumber {
+            lexeme: lexeme.to_string(),
+            line: self.line,
+            col: self.start_col,
+            value: value as LoxNumber,
+            integer,
+        })))
     }
 
     fn string(&mut self) -> Option<Token> {
+        let mut value = String::new();
+
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            let c = self.advance();
+
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    value.push(c);
+                }
+                '\\' => {
+                    if self.is_at_end() {
+                        break;
+                    }
+
+                    match self.advance() {
+                        '\\' => value.push('\\'),
+                        '"' => value.push('"'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        '0' => value.push('\0'),
+                        'a' => value.push('\u{07}'),
+                        // A backslash before a line break continues the string on the next line.
+                        '\n' => self.line += 1,
+                        other => self.errors.push(error(
+                            self.line,
+                            self.col - 2,
+                            &format!("Invalid escape sequence '\\{}'.", other),
+                        )),
+                    }
+                }
+                _ => value.push(c),
             }
-            let _ = self.advance();
         }
 
         if self.is_at_end() {
-            self.errors.borrow_mut().push(error(
+            self.errors.push(error(
                 self.line,
-                &"Unterminated string literal.".to_string(),
+                self.start_col,
+                "Unterminated string literal.",
             ));
 
             return None;
@@ -115,11 +282,43 @@ impl<'a> Scanner<'a> {
 
         let _ = self.advance();
 
-        return Some(Token::String(TokenValueString {
+        Some(Token::String(Box::new(TokenValueString {
             lexeme: self.source[self.start..self.current].to_string(),
             line: self.line,
-            value: self.source[self.start + 1..self.current - 1].to_string(),
-        }));
+            col: self.start_col,
+            value: LoxString::from(value),
+        })))
+    }
+
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.errors.push(error(
+                    self.line,
+                    self.start_col,
+                    "Unterminated block comment.",
+                ));
+
+                return;
+            }
+
+            if self.peek() == '*' && self.peek_next() == '/' {
+                let _ = self.advance();
+                let _ = self.advance();
+                depth -= 1;
+            } else if self.peek() == '/' && self.peek_next() == '*' {
+                let _ = self.advance();
+                let _ = self.advance();
+                depth += 1;
+            } else {
+                if self.peek() == '\n' {
+                    self.line += 1;
+                }
+                let _ = self.advance();
+            }
+        }
     }
 
     fn identifier(&mut self) -> Option<Token> {
@@ -130,17 +329,18 @@ impl<'a> Scanner<'a> {
             c = self.peek();
         }
 
-        return Some(Token::Keyword(TokenValueKeyword {
-            lexeme: self.source[self.start..self.current].to_string(),
+        Some(Token::Keyword(Box::new(TokenValueKeyword {
             line: self.line,
+            col: self.start_col,
             keyword: Keyword::from(&self.source[self.start..self.current]),
-        }));
+        })))
     }
 
     fn get_token_value(&self) -> TokenValue {
         TokenValue {
             lexeme: self.source[self.start..self.current].to_string(),
             line: self.line,
+            col: self.start_col,
         }
     }
 
@@ -154,7 +354,7 @@ impl<'a> Scanner<'a> {
         }
 
         let _ = self.advance();
-        return true;
+        true
     }
 }
 
@@ -163,6 +363,7 @@ impl<'a> Iterator for Scanner<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.start = self.current;
+        self.start_col = self.col;
 
         if self.at_the_end {
             return None;
@@ -170,53 +371,153 @@ impl<'a> Iterator for Scanner<'a> {
 
         if self.is_at_end() {
             self.at_the_end = true;
-            return Some(Token::Eof(TokenValueEof {
+            return Some(Token::Eof(Box::new(TokenValueEof {
                 line: self.line + 1,
-            }));
+                col: self.col,
+            })));
         }
 
         let c = self.advance();
 
         match c {
-            '(' => return Some(token_n!(self, LeftParen)),
-            ')' => return Some(token_n!(self, RightParen)),
-            '{' => return Some(token_n!(self, LeftBrace)),
-            '}' => return Some(token_n!(self, RightBrace)),
-            ',' => return Some(token_n!(self, Comma)),
-            '.' => return Some(token_n!(self, Dot)),
-            '-' => return Some(token_n!(self, Minus)),
-            '+' => return Some(token_n!(self, Plus)),
-            ';' => return Some(token_n!(self, Semicolon)),
-            '*' => return Some(token_n!(self, Star)),
-            '?' => return Some(token_n!(self, QuestionMark)),
-            ':' => return Some(token_n!(self, Colon)),
+            '(' => Some(token_n!(self, LeftParen)),
+            ')' => Some(token_n!(self, RightParen)),
+            '{' => Some(token_n!(self, LeftBrace)),
+            '}' => Some(token_n!(self, RightBrace)),
+            ',' => Some(token_n!(self, Comma)),
+            ';' => Some(token_n!(self, Semicolon)),
+            ':' => Some(token_n!(self, Colon)),
+            '~' => Some(token_n!(self, Tilde)),
+            '[' => Some(token_n!(self, LeftBracket)),
+            ']' => Some(token_n!(self, RightBracket)),
 
             '!' => {
                 if self.matching('=') {
-                    return Some(token_n!(self, BangEqual));
+                    Some(token_n!(self, BangEqual))
                 } else {
-                    return Some(token_n!(self, Bang));
+                    Some(token_n!(self, Bang))
                 }
             }
             '=' => {
                 if self.matching('=') {
-                    return Some(token_n!(self, EqualEqual));
+                    Some(token_n!(self, EqualEqual))
                 } else {
-                    return Some(token_n!(self, Equal));
+                    Some(token_n!(self, Equal))
                 }
             }
             '<' => {
+                if self.matching('<') {
+                    if self.matching('=') {
+                        Some(token_n!(self, ShiftLeftEqual))
+                    } else {
+                        Some(token_n!(self, ShiftLeft))
+                    }
+                } else if self.matching('=') {
+                    Some(token_n!(self, LessEqual))
+                } else {
+                    Some(token_n!(self, Less))
+                }
+            }
+            '-' => {
                 if self.matching('=') {
-                    return Some(token_n!(self, LessEqual));
+                    Some(token_n!(self, MinusEqual))
+                } else if self.matching('-') {
+                    Some(token_n!(self, MinusMinus))
                 } else {
-                    return Some(token_n!(self, Less));
+                    Some(token_n!(self, Minus))
                 }
             }
-            '>' => {
+            '+' => {
+                if self.matching('=') {
+                    Some(token_n!(self, PlusEqual))
+                } else if self.matching('+') {
+                    Some(token_n!(self, PlusPlus))
+                } else {
+                    Some(token_n!(self, Plus))
+                }
+            }
+            '?' => {
+                if self.matching('.') {
+                    Some(token_n!(self, QuestionDot))
+                } else if self.matching('?') {
+                    if self.matching('=') {
+                        Some(token_n!(self, QuestionQuestionEqual))
+                    } else {
+                        Some(token_n!(self, QuestionQuestion))
+                    }
+                } else {
+                    Some(token_n!(self, QuestionMark))
+                }
+            }
+            '.' => {
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.advance();
+                    self.advance();
+                    Some(token_n!(self, DotDotDot))
+                } else {
+                    Some(token_n!(self, Dot))
+                }
+            }
+            '*' => {
+                if self.matching('*') {
+                    if self.matching('=') {
+                        Some(token_n!(self, StarStarEqual))
+                    } else {
+                        Some(token_n!(self, StarStar))
+                    }
+                } else if self.matching('=') {
+                    Some(token_n!(self, StarEqual))
+                } else {
+                    Some(token_n!(self, Star))
+                }
+            }
+            '&' => {
+                if self.peek() == '&' && self.peek_next() == '=' {
+                    self.advance();
+                    self.advance();
+                    Some(token_n!(self, AmpAmpEqual))
+                } else if self.matching('=') {
+                    Some(token_n!(self, AmpersandEqual))
+                } else {
+                    Some(token_n!(self, Ampersand))
+                }
+            }
+            '|' => {
+                if self.peek() == '|' && self.peek_next() == '=' {
+                    self.advance();
+                    self.advance();
+                    Some(token_n!(self, PipePipeEqual))
+                } else if self.matching('=') {
+                    Some(token_n!(self, BitwiseOrEqual))
+                } else {
+                    Some(token_n!(self, BitwiseOr))
+                }
+            }
+            '^' => {
+                if self.matching('=') {
+                    Some(token_n!(self, CaretEqual))
+                } else {
+                    Some(token_n!(self, Caret))
+                }
+            }
+            '%' => {
                 if self.matching('=') {
-                    return Some(token_n!(self, GreaterEqual));
+                    Some(token_n!(self, PercentEqual))
+                } else {
+                    Some(token_n!(self, Percent))
+                }
+            }
+            '>' => {
+                if self.matching('>') {
+                    if self.matching('=') {
+                        Some(token_n!(self, ShiftRightEqual))
+                    } else {
+                        Some(token_n!(self, ShiftRight))
+                    }
+                } else if self.matching('=') {
+                    Some(token_n!(self, GreaterEqual))
                 } else {
-                    return Some(token_n!(self, Greater));
+                    Some(token_n!(self, Greater))
                 }
             }
 
@@ -226,25 +527,22 @@ impl<'a> Iterator for Scanner<'a> {
                         let _ = self.advance();
                     }
 
-                    return self.next();
+                    self.next()
                 } else if self.matching('*') {
-                    while self.peek() != '*' && self.peek_next() != '/' && !self.is_at_end() {
-                        let _ = self.advance();
-                    }
-
-                    let _ = self.advance();
-                    let _ = self.advance();
+                    self.block_comment();
 
-                    return self.next();
+                    self.next()
+                } else if self.matching('=') {
+                    Some(token_n!(self, SlashEqual))
                 } else {
-                    return Some(token_n!(self, Slash));
+                    Some(token_n!(self, Slash))
                 }
             }
 
-            ' ' | '\r' | '\t' => return self.next(),
+            ' ' | '\r' | '\t' => self.next(),
             '\n' => {
                 self.line += 1;
-                return self.next();
+                self.next()
             }
 
             '"' => {
@@ -252,15 +550,22 @@ impl<'a> Iterator for Scanner<'a> {
                     return Some(string_token);
                 }
 
-                return self.next();
+                self.next()
             }
 
+            '0' if self.peek() == 'x' || self.peek() == 'X' => {
+                if let Some(number_token) = self.hex_number() {
+                    return Some(number_token);
+                }
+
+                self.next()
+            }
             '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '0' => {
                 if let Some(number_token) = self.number() {
                     return Some(number_token);
                 }
 
-                return self.next();
+                self.next()
             }
 
             _ => {
@@ -269,15 +574,305 @@ impl<'a> Iterator for Scanner<'a> {
                         return Some(identifier_token);
                     }
 
-                    return self.next();
+                    self.next()
                 } else {
-                    self.errors
-                        .borrow_mut()
-                        .push(error(self.line, &format!("Unexpected character '{}'.", c)));
+                    self.errors.push(error(
+                        self.line,
+                        self.start_col,
+                        &format!("Unexpected character '{}'.", c),
+                    ));
 
-                    return self.next();
+                    self.next()
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<String> {
+        let (tokens, errors) = Scanner::new(source).finish();
+        assert!(errors.is_empty(), "unexpected scan errors");
+
+        tokens.iter().map(|token| token.to_string()).collect()
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let (_, errors) = Scanner::new(source).finish();
+
+        errors.iter().map(|err| err.to_string()).collect()
+    }
+
+    #[test]
+    fn scans_punctuation_and_operators() {
+        assert_eq!(
+            tokens("(){};,.-+*/ != == <= >="),
+            vec![
+                "LeftParen '('",
+                "RightParen ')'",
+                "LeftBrace '{'",
+                "RightBrace '}'",
+                "Semicolon ';'",
+                "Comma ','",
+                "Dot '.'",
+                "Minus '-'",
+                "Plus '+'",
+                "Star '*'",
+                "Slash '/'",
+                "BangEqual '!='",
+                "EqualEqual '=='",
+                "LessEqual '<='",
+                "GreaterEqual '>='",
+                "EOF",
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_literals_and_identifiers() {
+        assert_eq!(
+            tokens("var x = \"hi\" 12.5"),
+            vec![
+                "Identifier 'var'",
+                "Identifier 'x'",
+                "Equal '='",
+                "String \"hi\"",
+                "Number 12.5",
+                "EOF",
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_lines() {
+        let (tokens, _) = Scanner::new("a\nb\n\nc").finish();
+        let lines = tokens[..3]
+            .iter()
+            .map(|token| token.line())
+            .collect::<Vec<usize>>();
+
+        assert_eq!(lines, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn reports_unterminated_string() {
+        let (_, errors) = Scanner::new("\"abc").finish();
+
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn skips_block_comments() {
+        assert_eq!(tokens("/* comment */ 1"), vec!["Number 1", "EOF"]);
+        assert_eq!(tokens("/* with * stars **/ 1"), vec!["Number 1", "EOF"]);
+        assert_eq!(tokens("/* a */ /* b */ 1"), vec!["Number 1", "EOF"]);
+    }
+
+    #[test]
+    fn nests_block_comments() {
+        assert_eq!(
+            tokens("/* outer /* inner */ still outer */ 1"),
+            vec!["Number 1", "EOF"]
+        );
+    }
+
+    #[test]
+    fn reports_unterminated_block_comment() {
+        assert_eq!(
+            errors("1 /* eof"),
+            vec!["[line 1, col 3] Error: Unterminated block comment."]
+        );
+    }
+
+    fn string_value(source: &str) -> String {
+        let (tokens, errors) = Scanner::new(source).finish();
+        assert!(errors.is_empty(), "unexpected scan errors");
+
+        let Token::String(string) = &tokens[0] else {
+            panic!("expected a string token");
+        };
+
+        string.value.to_string()
+    }
+
+    #[test]
+    fn unescapes_string_escape_sequences() {
+        assert_eq!(string_value(r#""a\nb""#), "a\nb");
+        assert_eq!(
+            string_value(r#""\\ \" \n \r \t \0 \a""#),
+            "\\ \" \n \r \t \0 \u{07}"
+        );
+    }
+
+    #[test]
+    fn keeps_the_original_string_lexeme() {
+        let (tokens, _) = Scanner::new(r#""a\tb""#).finish();
+
+        assert_eq!(tokens[0].lexeme(), r#""a\tb""#);
+    }
+
+    #[test]
+    fn reports_invalid_escape_and_keeps_scanning() {
+        let (tokens, errors) = Scanner::new(r#""a\qb" 1"#).finish();
+
+        assert_eq!(
+            errors
+                .iter()
+                .map(|err| err.to_string())
+                .collect::<Vec<String>>(),
+            vec!["[line 1, col 3] Error: Invalid escape sequence '\\q'."]
+        );
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn continues_strings_after_an_escaped_line_break() {
+        assert_eq!(string_value("\"a\\\nb\""), "ab");
+
+        let (tokens, _) = Scanner::new("\"a\\\nb\" x").finish();
+        assert_eq!(tokens[1].line(), 2);
+    }
+
+    #[test]
+    fn scans_hexadecimal_literals() {
+        assert_eq!(
+            tokens("0xFF 0x1A2B3C 0Xff"),
+            vec!["Number 255", "Number 1715004", "Number 255", "EOF"]
+        );
+    }
+
+    #[test]
+    fn rejects_hexadecimal_prefix_without_digits() {
+        assert_eq!(
+            errors("0x;"),
+            vec!["[line 1, col 1] Error: Invalid hexadecimal literal '0x'."]
+        );
+    }
+
+    #[test]
+    fn scans_scientific_notation() {
+        assert_eq!(
+            tokens("1e0 2.5E-3 1.0e+6"),
+            vec!["Number 1", "Number 0.0025", "Number 1000000", "EOF"]
+        );
+    }
+
+    #[test]
+    fn scans_extreme_exponents() {
+        let (tokens, errors) = Scanner::new("1E+300 1e-308").finish();
+        assert!(errors.is_empty());
+
+        let values = tokens[..2]
+            .iter()
+            .map(|token| match token {
+                Token::Number(number) => number.value,
+                _ => panic!("expected a number token"),
+            })
+            .collect::<Vec<LoxNumber>>();
+
+        assert_eq!(values, vec![1e300, 1e-308]);
+    }
+
+    #[test]
+    fn rejects_exponent_without_digits() {
+        assert_eq!(
+            errors("1e;"),
+            vec!["[line 1, col 1] Error: Missing exponent digits in number literal '1e'."]
+        );
+    }
+
+    #[test]
+    fn strips_underscore_separators() {
+        assert_eq!(
+            tokens("1_000_000 0xFF_FF 1_0.5_0"),
+            vec!["Number 1000000", "Number 65535", "Number 10.5", "EOF"]
+        );
+
+        let (tokens, _) = Scanner::new("1_000").finish();
+        assert_eq!(tokens[0].lexeme(), "1_000");
+    }
+
+    #[test]
+    fn rejects_misplaced_underscores() {
+        assert_eq!(
+            errors("123_;"),
+            vec![
+                "[line 1, col 1] Error: Underscores in number literal '123_' must be between digits."
+            ]
+        );
+        assert_eq!(
+            errors("1__0;"),
+            vec![
+                "[line 1, col 1] Error: Underscores in number literal '1__0' must be between digits."
+            ]
+        );
+        assert_eq!(tokens("_123"), vec!["Identifier '_123'", "EOF"]);
+    }
+
+    #[test]
+    fn reports_error_columns() {
+        assert_eq!(
+            errors("var x = 1;\nprint x @ 2;"),
+            vec!["[line 2, col 9] Error: Unexpected character '@'."]
+        );
+    }
+
+    #[test]
+    fn records_token_columns() {
+        let (tokens, _) = Scanner::new("var x\n  = 1;").finish();
+        let columns = tokens[..4]
+            .iter()
+            .map(|token| token.col())
+            .collect::<Vec<usize>>();
+
+        assert_eq!(columns, vec![1, 5, 3, 5]);
+    }
+
+    #[test]
+    fn scans_identifiers_with_leading_underscore() {
+        assert_eq!(
+            tokens("var _private = 1;"),
+            vec![
+                "Identifier 'var'",
+                "Identifier '_private'",
+                "Equal '='",
+                "Number 1",
+                "Semicolon ';'",
+                "EOF",
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_underscores_anywhere_in_identifiers() {
+        assert_eq!(
+            tokens("_foo foo_bar _ __init__"),
+            vec![
+                "Identifier '_foo'",
+                "Identifier 'foo_bar'",
+                "Identifier '_'",
+                "Identifier '__init__'",
+                "EOF",
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_digits_followed_by_letters() {
+        assert_eq!(
+            tokens("123abc"),
+            vec!["Number 123", "Identifier 'abc'", "EOF"]
+        );
+    }
+
+    #[test]
+    fn leaves_a_dot_without_digits_out_of_the_number() {
+        assert_eq!(
+            tokens("1.foo"),
+            vec!["Number 1", "Dot '.'", "Identifier 'foo'", "EOF"]
+        );
+    }
+}
@@ -1,88 +1,345 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
+use std::{collections::HashMap, io::Write, sync::Arc};
 
 use crate::{
+    LoxError,
     environment::Environment,
-    expression::Expr,
-    lox_type::{LoxFunction, LoxType},
-    token::Token,
+    expression::{Expr, VariableExpr},
+    lox_type::{LoxClass, LoxFunction, LoxString, LoxType},
+    runtime_error,
 };
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct FunctionStatement {
     pub name: String,
-    pub params: Vec<Token>,
-    pub body: Box<Statement>,
+    pub params: Vec<(String, usize, Option<Expr>)>,
+    pub is_variadic: bool,
+    pub body: Vec<Statement>,
+    pub line: usize,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ClassStatement {
+    pub name: String,
+    pub superclass: Option<VariableExpr>,
+    pub methods: Vec<FunctionStatement>,
+    pub line: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct VarStatement {
     pub name: String,
     pub initializer: Option<Expr>,
+    pub is_const: bool,
+    pub line: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct IfStatement {
     pub condition: Expr,
     pub then_branch: Box<Statement>,
     pub else_branch: Option<Box<Statement>>,
+    pub line: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct WhileStatement {
     pub condition: Expr,
     pub body: Box<Statement>,
     pub in_for_loop: bool,
+    pub line: usize,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct DoWhileStatement {
+    pub body: Box<Statement>,
+    pub condition: Expr,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SwitchStatement {
+    pub discriminant: Expr,
+    pub cases: Vec<(Expr, Vec<Statement>)>,
+    pub default: Option<Vec<Statement>>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct LabeledStatement {
+    pub label: String,
+    pub body: Box<Statement>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ForInStatement {
+    pub variable: String,
+    pub iterable: Expr,
+    pub body: Box<Statement>,
+    pub line: usize,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TryStatement {
+    pub body: Box<Statement>,
+    pub catch_var: Option<String>,
+    pub catch_body: Option<Box<Statement>>,
+    pub finally_body: Option<Box<Statement>>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ThrowStatement {
+    pub value: Expr,
+    pub line: usize,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct JumpStatement {
+    pub line: usize,
+    pub label: Option<String>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct ReturnStatement {
-    pub keyword: Token,
+    pub line: usize,
     pub value: Option<Expr>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Statement {
     Expression(Expr),
     Print(Expr),
     Var(VarStatement),
     Block(Vec<Statement>),
     If(IfStatement),
+    Switch(SwitchStatement),
     While(WhileStatement),
+    DoWhile(DoWhileStatement),
+    ForIn(ForInStatement),
     Function(FunctionStatement),
-    Break,
-    Continue,
+    Class(ClassStatement),
+    Labeled(LabeledStatement),
+    Break(JumpStatement),
+    Continue(JumpStatement),
     Return(ReturnStatement),
+    Try(TryStatement),
+    Throw(ThrowStatement),
 }
 
 pub enum StatementSignal {
-    Break,
-    Continue,
-    Return(Option<LoxType>),
+    Break(usize),
+    Continue(usize),
+    BreakLabel(String, usize),
+    ContinueLabel(String, usize),
+    Return(Option<LoxType>, usize),
+    Error(LoxError),
+}
+
+impl std::fmt::Display for FunctionStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params = self
+            .params
+            .iter()
+            .map(|(param, _, default)| match default {
+                Some(default) => format!("(= {} {})", param, default),
+                None => param.clone(),
+            })
+            .collect::<Vec<String>>();
+
+        if self.is_variadic
+            && let Some(rest) = params.last_mut()
+        {
+            *rest = format!("...{}", rest);
+        }
+
+        let params = params.join(" ");
+
+        if self.name.is_empty() {
+            write!(f, "(fun ({})", params)?;
+        } else {
+            write!(f, "(fun {} ({})", self.name, params)?;
+        }
+
+        for stmt in &self.body {
+            write!(f, " {}", stmt)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+impl std::fmt::Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statement::Expression(expr) => write!(f, "(; {})", expr),
+            Statement::Print(expr) => write!(f, "(print {})", expr),
+            Statement::Var(vs) => {
+                let keyword = if vs.is_const { "const" } else { "var" };
+
+                match &vs.initializer {
+                    Some(initializer) => write!(f, "({} {} {})", keyword, vs.name, initializer),
+                    None => write!(f, "({} {})", keyword, vs.name),
+                }
+            }
+            Statement::Block(block) => {
+                write!(f, "(block")?;
+
+                for stmt in block {
+                    write!(f, " {}", stmt)?;
+                }
+
+                write!(f, ")")
+            }
+            Statement::If(is) => match &is.else_branch {
+                Some(else_branch) => write!(
+                    f,
+                    "(if {} {} {})",
+                    is.condition, is.then_branch, else_branch
+                ),
+                None => write!(f, "(if {} {})", is.condition, is.then_branch),
+            },
+            Statement::While(ws) => write!(f, "(while {} {})", ws.condition, ws.body),
+            Statement::DoWhile(ds) => write!(f, "(do {} {})", ds.body, ds.condition),
+            Statement::ForIn(fs) => {
+                write!(f, "(for-in {} {} {})", fs.variable, fs.iterable, fs.body)
+            }
+            Statement::Function(fs) => write!(f, "{}", fs),
+            Statement::Class(cs) => {
+                write!(f, "(class {}", cs.name)?;
+
+                if let Some(superclass) = &cs.superclass {
+                    write!(f, " < {}", superclass.name)?;
+                }
+
+                for method in &cs.methods {
+                    write!(f, " {}", method)?;
+                }
+
+                write!(f, ")")
+            }
+            Statement::Switch(ss) => {
+                write!(f, "(switch {}", ss.discriminant)?;
+
+                for (value, body) in &ss.cases {
+                    write!(f, " (case {}", value)?;
+
+                    for stmt in body {
+                        write!(f, " {}", stmt)?;
+                    }
+
+                    write!(f, ")")?;
+                }
+
+                if let Some(default) = &ss.default {
+                    write!(f, " (default")?;
+
+                    for stmt in default {
+                        write!(f, " {}", stmt)?;
+                    }
+
+                    write!(f, ")")?;
+                }
+
+                write!(f, ")")
+            }
+            Statement::Labeled(ls) => write!(f, "(label {} {})", ls.label, ls.body),
+            Statement::Break(js) => match &js.label {
+                Some(label) => write!(f, "(break {})", label),
+                None => write!(f, "(break)"),
+            },
+            Statement::Continue(js) => match &js.label {
+                Some(label) => write!(f, "(continue {})", label),
+                None => write!(f, "(continue)"),
+            },
+            Statement::Return(rs) => match &rs.value {
+                Some(value) => write!(f, "(return {})", value),
+                None => write!(f, "(return)"),
+            },
+            Statement::Try(ts) => {
+                write!(f, "(try {}", ts.body)?;
+
+                if let Some(catch_body) = &ts.catch_body {
+                    write!(f, " (catch")?;
+
+                    if let Some(catch_var) = &ts.catch_var {
+                        write!(f, " {}", catch_var)?;
+                    }
+
+                    write!(f, " {})", catch_body)?;
+                }
+
+                if let Some(finally_body) = &ts.finally_body {
+                    write!(f, " (finally {})", finally_body)?;
+                }
+
+                write!(f, ")")
+            }
+            Statement::Throw(ts) => write!(f, "(throw {})", ts.value),
+        }
+    }
+}
+
+impl From<LoxError> for StatementSignal {
+    fn from(err: LoxError) -> Self {
+        StatementSignal::Error(err)
+    }
 }
+
 impl<'a> Statement {
     pub fn eval(&self, env: &'a mut Environment) -> Result<(), StatementSignal> {
-        return match self {
+        match self {
             Statement::Expression(expr) => {
-                let _value = expr.eval(env);
+                let _value = expr.eval(env)?;
                 Ok(())
             }
             Statement::Print(expr) => {
-                let value = expr.eval(env);
+                let value = expr.eval(env)?;
                 println!("{}", value);
+                let _ = std::io::stdout().flush();
                 Ok(())
             }
             Statement::Function(fs) => {
-                let lox_fn = LoxType::Function(Arc::new(Mutex::new(LoxFunction {
-                    name: fs.name.clone(),
-                    params: fs.params.clone(),
-                    body: *fs.body.clone(),
-                    closure: env.clone(),
-                })));
+                let lox_fn = LoxType::Function(Arc::new(LoxFunction::new(fs, env.clone())));
 
-                env.define(fs.name.clone(), lox_fn);
+                env.declare(fs.name.clone(), lox_fn, false, fs.line)?;
+
+                Ok(())
+            }
+            Statement::Class(cs) => {
+                let superclass = match &cs.superclass {
+                    Some(sc) => match env.get_at(sc.depth, &sc.name, sc.line)? {
+                        LoxType::Class(class) => Some(class),
+                        _ => {
+                            return Err(
+                                runtime_error!(sc.line, "Superclass must be a class.").into()
+                            );
+                        }
+                    },
+                    None => None,
+                };
+
+                let method_env = match &superclass {
+                    Some(class) => {
+                        let mut super_env = Environment::new(Some(env.clone()), HashMap::new());
+                        super_env.define("super".to_string(), LoxType::Class(class.clone()));
+                        super_env
+                    }
+                    None => env.clone(),
+                };
+
+                let methods = cs
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        let function = LoxFunction::new(method, method_env.clone());
+                        (method.name.clone(), Arc::new(function))
+                    })
+                    .collect::<HashMap<String, Arc<LoxFunction>>>();
+
+                let class = LoxType::Class(Arc::new(LoxClass {
+                    name: cs.name.clone(),
+                    superclass,
+                    methods,
+                }));
+
+                env.declare(cs.name.clone(), class, false, cs.line)?;
 
                 Ok(())
             }
@@ -90,10 +347,10 @@ impl<'a> Statement {
                 let mut value = LoxType::Nil;
 
                 if let Some(expr) = &vs.initializer {
-                    value = expr.eval(env);
+                    value = expr.eval(env)?;
                 }
 
-                env.define(vs.name.clone(), value);
+                env.declare(vs.name.clone(), value, vs.is_const, vs.line)?;
 
                 Ok(())
             }
@@ -101,21 +358,13 @@ impl<'a> Statement {
                 let mut block_env = Environment::new(Some(env.clone()), HashMap::new());
 
                 for stmt in block {
-                    let res = stmt.eval(&mut block_env);
-
-                    if res.is_err() {
-                        env.reset(&block_env.enclosing.unwrap());
-
-                        return res;
-                    }
+                    stmt.eval(&mut block_env)?;
                 }
 
-                env.reset(&block_env.enclosing.unwrap());
-
                 Ok(())
             }
             Statement::If(is) => {
-                if is.condition.eval(env).is_truthy() {
+                if is.condition.eval(env)?.is_truthy() {
                     is.then_branch.eval(env)?;
                 } else if let Some(else_branch) = &is.else_branch {
                     else_branch.eval(env)?;
@@ -123,26 +372,134 @@ impl<'a> Statement {
 
                 Ok(())
             }
+            Statement::Switch(ss) => {
+                let discriminant = ss.discriminant.eval(env)?;
+
+                let mut matched = None;
+
+                for (i, (value, _)) in ss.cases.iter().enumerate() {
+                    if value.eval(env)? == discriminant {
+                        matched = Some(i);
+                        break;
+                    }
+                }
+
+                // A case without statements falls through to the next one, and the last
+                // case falls through to the default.
+                let body = match matched {
+                    Some(i) => ss.cases[i..]
+                        .iter()
+                        .map(|(_, body)| body)
+                        .find(|body| !body.is_empty())
+                        .or(ss.default.as_ref()),
+                    None => ss.default.as_ref(),
+                };
+
+                let Some(body) = body else {
+                    return Ok(());
+                };
+
+                let mut switch_env = Environment::new(Some(env.clone()), HashMap::new());
+
+                for stmt in body {
+                    match stmt.eval(&mut switch_env) {
+                        Ok(()) => {}
+                        Err(StatementSignal::Break(_)) => break,
+                        Err(ss) => return Err(ss),
+                    }
+                }
+
+                Ok(())
+            }
+            Statement::While(_) | Statement::DoWhile(_) | Statement::ForIn(_) => {
+                self.eval_loop(env, None)
+            }
+            Statement::Labeled(ls) => ls.body.eval_loop(env, Some(&ls.label)),
+            Statement::Break(js) => Err(match &js.label {
+                Some(label) => StatementSignal::BreakLabel(label.clone(), js.line),
+                None => StatementSignal::Break(js.line),
+            }),
+            Statement::Continue(js) => Err(match &js.label {
+                Some(label) => StatementSignal::ContinueLabel(label.clone(), js.line),
+                None => StatementSignal::Continue(js.line),
+            }),
+            Statement::Return(rs) => {
+                let value = match &rs.value {
+                    Some(expr) => Some(expr.eval(env)?),
+                    None => None,
+                };
+
+                Err(StatementSignal::Return(value, rs.line))
+            }
+            Statement::Try(ts) => {
+                let result = match (ts.body.eval(env), &ts.catch_body) {
+                    (Err(StatementSignal::Error(err)), Some(catch_body)) => {
+                        let caught = match err {
+                            LoxError::Runtime { message, .. } => {
+                                LoxType::String(LoxString::from(message))
+                            }
+                            LoxError::Thrown { value, .. } => value,
+                            err => return Err(err.into()),
+                        };
+
+                        let mut catch_env = Environment::new(Some(env.clone()), HashMap::new());
+
+                        if let Some(catch_var) = &ts.catch_var {
+                            catch_env.define(catch_var.clone(), caught);
+                        }
+
+                        catch_body.eval(&mut catch_env)
+                    }
+                    (result, _) => result,
+                };
+
+                if let Some(finally_body) = &ts.finally_body {
+                    finally_body.eval(env)?;
+                }
+
+                result
+            }
+            Statement::Throw(ts) => {
+                let value = ts.value.eval(env)?;
+
+                Err(LoxError::Thrown {
+                    line: ts.line,
+                    value,
+                }
+                .into())
+            }
+        }
+    }
+
+    /// Runs a loop statement, treating `break`/`continue` aimed at `label` as its own.
+    fn eval_loop(
+        &self,
+        env: &'a mut Environment,
+        label: Option<&str>,
+    ) -> Result<(), StatementSignal> {
+        match self {
             Statement::While(ws) => {
-                while ws.condition.eval(env).is_truthy() {
+                while ws.condition.eval(env)?.is_truthy() {
                     let res = ws.body.eval(env);
 
                     if let Err(ss) = res {
-                        match ss {
-                            StatementSignal::Break => break,
-                            StatementSignal::Continue => {
-                                if ws.in_for_loop {
-                                    let Statement::Block(ref loop_block) = *ws.body else {
-                                        continue;
-                                    };
-
-                                    let _ = loop_block.last().unwrap().eval(env);
-                                }
+                        if ss.breaks_loop(label)? {
+                            break;
+                        }
 
+                        if ws.in_for_loop {
+                            let Statement::Block(ref loop_block) = *ws.body else {
                                 continue;
-                            }
-                            StatementSignal::Return(_) => {
-                                return Err(ss);
+                            };
+
+                            if let Some(last) = loop_block.last() {
+                                let mut increment_env =
+                                    Environment::new(Some(env.clone()), HashMap::new());
+                                if let Err(StatementSignal::Error(err)) =
+                                    last.eval(&mut increment_env)
+                                {
+                                    return Err(StatementSignal::Error(err));
+                                }
                             }
                         }
                     }
@@ -150,17 +507,228 @@ impl<'a> Statement {
 
                 Ok(())
             }
-            Statement::Break => {
-                return Err(StatementSignal::Break);
+            Statement::DoWhile(ds) => {
+                loop {
+                    if let Err(ss) = ds.body.eval(env)
+                        && ss.breaks_loop(label)?
+                    {
+                        break;
+                    }
+
+                    if !ds.condition.eval(env)?.is_truthy() {
+                        break;
+                    }
+                }
+
+                Ok(())
             }
-            Statement::Continue => {
-                return Err(StatementSignal::Continue);
+            Statement::ForIn(fs) => {
+                let items = match fs.iterable.eval(env)? {
+                    LoxType::Array(items) => items.lock().unwrap().clone(),
+                    LoxType::String(s) => s
+                        .chars()
+                        .map(|c| LoxType::String(LoxString::from(c.to_string())))
+                        .collect(),
+                    _ => {
+                        return Err(runtime_error!(
+                            fs.line,
+                            "Can only iterate over arrays and strings."
+                        )
+                        .into());
+                    }
+                };
+
+                for item in items {
+                    let mut loop_env = Environment::new(Some(env.clone()), HashMap::new());
+                    loop_env.define(fs.variable.clone(), item);
+
+                    if let Err(ss) = fs.body.eval(&mut loop_env)
+                        && ss.breaks_loop(label)?
+                    {
+                        break;
+                    }
+                }
+
+                Ok(())
             }
-            Statement::Return(rs) => {
-                return Err(StatementSignal::Return(
-                    rs.value.as_ref().map(|r| r.eval(env)),
-                ));
+            // `for` loops with an initializer are desugared into a block around the loop.
+            Statement::Block(block) => {
+                let mut block_env = Environment::new(Some(env.clone()), HashMap::new());
+
+                for stmt in block {
+                    stmt.eval_loop(&mut block_env, label)?;
+                }
+
+                Ok(())
             }
-        };
+            _ => self.eval(env),
+        }
+    }
+}
+
+impl StatementSignal {
+    /// Tells whether this signal ends the loop labeled `label` (`true`) or only its current
+    /// iteration (`false`). Signals aimed elsewhere are handed back to be propagated.
+    fn breaks_loop(self, label: Option<&str>) -> Result<bool, StatementSignal> {
+        match self {
+            StatementSignal::Break(_) => Ok(true),
+            StatementSignal::Continue(_) => Ok(false),
+            StatementSignal::BreakLabel(ref l, _) if Some(l.as_str()) == label => Ok(true),
+            StatementSignal::ContinueLabel(ref l, _) if Some(l.as_str()) == label => Ok(false),
+            ss => Err(ss),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{error, global};
+
+    #[test]
+    fn try_catches_native_errors() {
+        let source = "var caught; try { Math.sqrt(\"x\"); } catch (e) { caught = e; }";
+
+        assert_eq!(
+            global(source, "caught"),
+            "Argument 1 of 'sqrt' must be a number."
+        );
+    }
+
+    #[test]
+    fn try_catches_array_callback_errors() {
+        let source = "
+            var caught;
+            try { [1].map(fun (x) { throw \"boom\"; }); } catch (e) { caught = e; }
+        ";
+
+        assert_eq!(global(source, "caught"), "boom");
+    }
+
+    #[test]
+    fn finally_runs_after_catch() {
+        let source = "
+            var log = \"\";
+            try { [1].forEach(fun (x) { return nope; }); }
+            catch (e) { log = log + \"catch \"; }
+            finally { log = log + \"finally\"; }
+        ";
+
+        assert_eq!(global(source, "log"), "catch finally");
+    }
+
+    #[test]
+    fn catch_binds_the_thrown_value() {
+        let source = "
+            class Boom { init(code) { this.code = code; } }
+            var kind;
+            try { throw 42; } catch (e) { kind = typeof e; }
+            var code;
+            try { throw Boom(7); } catch (e) { code = e.code; }
+        ";
+
+        assert_eq!(global(source, "kind"), "number");
+        assert_eq!(global(source, "code"), "7");
+        assert_eq!(error("throw 42;"), "[line 1] Error: 42");
+    }
+
+    #[test]
+    fn finally_without_catch_rethrows() {
+        let source = "
+            var log = \"\";
+            try { try { throw \"boom\"; } finally { log = log + \"finally \"; } }
+            catch (e) { log = log + e; }
+        ";
+
+        assert_eq!(global(source, "log"), "finally boom");
+    }
+
+    #[test]
+    fn iterates_arrays_and_strings() {
+        let source = "
+            var sum = 0;
+            for (var n in [1, 2, 3]) sum = sum + n;
+            var chars = \"\";
+            for (var c in \"héllo\") {
+                if (c == \"l\") continue;
+                chars = c + chars;
+            }
+            var first = nil;
+            for (var n in [4, 5]) { first = n; break; }
+        ";
+
+        assert_eq!(global(source, "sum"), "6");
+        assert_eq!(global(source, "chars"), "oéh");
+        assert_eq!(global(source, "first"), "4");
+    }
+
+    #[test]
+    fn do_while_runs_the_body_at_least_once() {
+        let source = "
+            var once = 0;
+            do once = once + 1; while (false);
+            var i = 0;
+            var seen = \"\";
+            do {
+                i = i + 1;
+                if (i == 2) continue;
+                if (i == 4) break;
+                seen = seen + i;
+            } while (i < 10);
+        ";
+
+        assert_eq!(global(source, "once"), "1");
+        assert_eq!(global(source, "seen"), "13");
+    }
+
+    #[test]
+    fn breaks_and_continues_outer_loops() {
+        let source = "
+            var pairs = \"\";
+            outer: for (var i = 0; i < 3; i = i + 1) {
+                for (var j = 0; j < 3; j = j + 1) {
+                    if (j == 1) continue outer;
+                    if (i == 2) break outer;
+                    pairs = pairs + i + j + \" \";
+                }
+            }
+        ";
+
+        assert_eq!(global(source, "pairs"), "00 10 ");
+    }
+
+    #[test]
+    fn dispatches_switch_cases() {
+        let source = "
+            fun name(n) {
+                var result = \"\";
+                switch (n) {
+                    case 1: result = \"one\";
+                    case 2:
+                    case 3: result = \"two or three\";
+                    case 4: result = \"four\"; break; result = \"unreachable\";
+                    default: result = \"other\";
+                }
+                return result;
+            }
+            var one = name(1);
+            var two = name(2);
+            var three = name(3);
+            var four = name(4);
+            var other = name(5);
+        ";
+
+        assert_eq!(global(source, "one"), "one");
+        assert_eq!(global(source, "two"), "two or three");
+        assert_eq!(global(source, "three"), "two or three");
+        assert_eq!(global(source, "four"), "four");
+        assert_eq!(global(source, "other"), "other");
+    }
+
+    #[test]
+    fn allows_shadowing_constants() {
+        let source = "const x = 1; var inner; { var x = 2; x = 3; inner = x; }";
+
+        assert_eq!(global(source, "inner"), "3");
+        assert_eq!(global(source, "x"), "1");
     }
 }
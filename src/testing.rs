@@ -0,0 +1,19 @@
+use crate::Interpreter;
+
+pub fn global(source: &str, name: &str) -> String {
+    let mut interpreter = Interpreter::new();
+    interpreter.run_source(source).unwrap();
+
+    interpreter.get_global(name).unwrap().to_string()
+}
+
+pub fn eval(source: &str) -> String {
+    Interpreter::new().eval_expr(source).unwrap().to_string()
+}
+
+pub fn error(source: &str) -> String {
+    match Interpreter::new().run_source(source) {
+        Ok(_) => String::new(),
+        Err(err) => err.to_string().trim_end().to_string(),
+    }
+}
@@ -1,57 +1,73 @@
-use std::fmt;
+use std::{borrow::Cow, fmt};
 
-use crate::lox_type::{LoxNumber, LoxString};
+use crate::lox_type::{LoxInteger, LoxNumber, LoxString};
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct TokenValue {
     pub lexeme: String,
     pub line: usize,
+    pub col: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct TokenValueString {
     pub lexeme: String,
     pub line: usize,
+    pub col: usize,
     pub value: LoxString,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct TokenValueNumber {
     pub lexeme: String,
     pub line: usize,
+    pub col: usize,
     pub value: LoxNumber,
+    pub integer: Option<LoxInteger>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct TokenValueKeyword {
-    pub lexeme: String,
     pub line: usize,
+    pub col: usize,
     pub keyword: Keyword,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct TokenValueEof {
     pub line: usize,
+    pub col: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Keyword {
     And,
     Break,
+    Case,
+    Catch,
     Continue,
+    Default,
+    Do,
     Class,
+    Const,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Switch,
     This,
+    Throw,
     True,
+    Try,
+    Typeof,
     Var,
     While,
     Identifier(String),
@@ -62,20 +78,31 @@ impl From<&str> for Keyword {
         match s {
             "and" => Keyword::And,
             "break" => Keyword::Break,
+            "case" => Keyword::Case,
+            "catch" => Keyword::Catch,
             "continue" => Keyword::Continue,
+            "default" => Keyword::Default,
+            "do" => Keyword::Do,
             "class" => Keyword::Class,
+            "const" => Keyword::Const,
             "else" => Keyword::Else,
             "false" => Keyword::False,
+            "finally" => Keyword::Finally,
             "fun" => Keyword::Fun,
             "for" => Keyword::For,
             "if" => Keyword::If,
+            "in" => Keyword::In,
             "nil" => Keyword::Nil,
             "or" => Keyword::Or,
             "print" => Keyword::Print,
             "return" => Keyword::Return,
             "super" => Keyword::Super,
+            "switch" => Keyword::Switch,
             "this" => Keyword::This,
+            "throw" => Keyword::Throw,
             "true" => Keyword::True,
+            "try" => Keyword::Try,
+            "typeof" => Keyword::Typeof,
             "var" => Keyword::Var,
             "while" => Keyword::While,
             _ => Keyword::Identifier(s.to_string()),
@@ -83,70 +110,116 @@ impl From<&str> for Keyword {
     }
 }
 
-impl fmt::Display for Keyword {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Keyword {
+    pub fn lexeme(&self) -> Cow<'_, str> {
         match self {
-            Keyword::And => write!(f, "and"),
-            Keyword::Break => write!(f, "break"),
-            Keyword::Continue => write!(f, "continue"),
-            Keyword::Class => write!(f, "class"),
-            Keyword::Else => write!(f, "else"),
-            Keyword::False => write!(f, "false"),
-            Keyword::Fun => write!(f, "fun"),
-            Keyword::For => write!(f, "for"),
-            Keyword::If => write!(f, "if"),
-            Keyword::Nil => write!(f, "nil"),
-            Keyword::Or => write!(f, "or"),
-            Keyword::Print => write!(f, "print"),
-            Keyword::Return => write!(f, "return"),
-            Keyword::Super => write!(f, "super"),
-            Keyword::This => write!(f, "this"),
-            Keyword::True => write!(f, "true"),
-            Keyword::Var => write!(f, "var"),
-            Keyword::While => write!(f, "while"),
-            Keyword::Identifier(s) => write!(f, "{s}"),
+            Keyword::And => Cow::Borrowed("and"),
+            Keyword::Break => Cow::Borrowed("break"),
+            Keyword::Case => Cow::Borrowed("case"),
+            Keyword::Catch => Cow::Borrowed("catch"),
+            Keyword::Continue => Cow::Borrowed("continue"),
+            Keyword::Default => Cow::Borrowed("default"),
+            Keyword::Do => Cow::Borrowed("do"),
+            Keyword::Class => Cow::Borrowed("class"),
+            Keyword::Const => Cow::Borrowed("const"),
+            Keyword::Else => Cow::Borrowed("else"),
+            Keyword::False => Cow::Borrowed("false"),
+            Keyword::Finally => Cow::Borrowed("finally"),
+            Keyword::Fun => Cow::Borrowed("fun"),
+            Keyword::For => Cow::Borrowed("for"),
+            Keyword::If => Cow::Borrowed("if"),
+            Keyword::In => Cow::Borrowed("in"),
+            Keyword::Nil => Cow::Borrowed("nil"),
+            Keyword::Or => Cow::Borrowed("or"),
+            Keyword::Print => Cow::Borrowed("print"),
+            Keyword::Return => Cow::Borrowed("return"),
+            Keyword::Super => Cow::Borrowed("super"),
+            Keyword::Switch => Cow::Borrowed("switch"),
+            Keyword::This => Cow::Borrowed("this"),
+            Keyword::Throw => Cow::Borrowed("throw"),
+            Keyword::True => Cow::Borrowed("true"),
+            Keyword::Try => Cow::Borrowed("try"),
+            Keyword::Typeof => Cow::Borrowed("typeof"),
+            Keyword::Var => Cow::Borrowed("var"),
+            Keyword::While => Cow::Borrowed("while"),
+            Keyword::Identifier(s) => Cow::Borrowed(s),
         }
     }
 }
 
-#[derive(Clone)]
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lexeme())
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub enum Token {
     // Single character tokens.
-    LeftParen(TokenValue),
-    RightParen(TokenValue),
-    LeftBrace(TokenValue),
-    RightBrace(TokenValue),
-    Comma(TokenValue),
-    Dot(TokenValue),
-    Minus(TokenValue),
-    Plus(TokenValue),
-    Semicolon(TokenValue),
-    Slash(TokenValue),
-    Star(TokenValue),
-    QuestionMark(TokenValue),
-    Colon(TokenValue),
+    LeftParen(Box<TokenValue>),
+    RightParen(Box<TokenValue>),
+    LeftBrace(Box<TokenValue>),
+    RightBrace(Box<TokenValue>),
+    Comma(Box<TokenValue>),
+    Dot(Box<TokenValue>),
+    Minus(Box<TokenValue>),
+    Plus(Box<TokenValue>),
+    Semicolon(Box<TokenValue>),
+    Slash(Box<TokenValue>),
+    Star(Box<TokenValue>),
+    Percent(Box<TokenValue>),
+    QuestionMark(Box<TokenValue>),
+    QuestionDot(Box<TokenValue>),
+    QuestionQuestion(Box<TokenValue>),
+    Colon(Box<TokenValue>),
+    Ampersand(Box<TokenValue>),
+    BitwiseOr(Box<TokenValue>),
+    Caret(Box<TokenValue>),
+    Tilde(Box<TokenValue>),
+    LeftBracket(Box<TokenValue>),
+    RightBracket(Box<TokenValue>),
 
     // One or two character tokens.
-    Bang(TokenValue),
-    BangEqual(TokenValue),
-    Equal(TokenValue),
-    EqualEqual(TokenValue),
-    Greater(TokenValue),
-    GreaterEqual(TokenValue),
-    Less(TokenValue),
-    LessEqual(TokenValue),
+    Bang(Box<TokenValue>),
+    BangEqual(Box<TokenValue>),
+    Equal(Box<TokenValue>),
+    EqualEqual(Box<TokenValue>),
+    Greater(Box<TokenValue>),
+    GreaterEqual(Box<TokenValue>),
+    Less(Box<TokenValue>),
+    LessEqual(Box<TokenValue>),
+    PlusEqual(Box<TokenValue>),
+    MinusEqual(Box<TokenValue>),
+    StarEqual(Box<TokenValue>),
+    SlashEqual(Box<TokenValue>),
+    PercentEqual(Box<TokenValue>),
+    StarStar(Box<TokenValue>),
+    StarStarEqual(Box<TokenValue>),
+    PlusPlus(Box<TokenValue>),
+    MinusMinus(Box<TokenValue>),
+    ShiftLeft(Box<TokenValue>),
+    ShiftRight(Box<TokenValue>),
+    AmpersandEqual(Box<TokenValue>),
+    BitwiseOrEqual(Box<TokenValue>),
+    CaretEqual(Box<TokenValue>),
+    ShiftLeftEqual(Box<TokenValue>),
+    ShiftRightEqual(Box<TokenValue>),
+    AmpAmpEqual(Box<TokenValue>),
+    PipePipeEqual(Box<TokenValue>),
+    QuestionQuestionEqual(Box<TokenValue>),
+    DotDotDot(Box<TokenValue>),
 
     // Literals.
-    Keyword(TokenValueKeyword),
-    String(TokenValueString),
-    Number(TokenValueNumber),
+    Keyword(Box<TokenValueKeyword>),
+    String(Box<TokenValueString>),
+    Number(Box<TokenValueNumber>),
 
-    Eof(TokenValueEof),
+    Eof(Box<TokenValueEof>),
 }
 
 impl Token {
     pub fn line(&self) -> usize {
-        let l = match self {
+        match self {
             Token::Keyword(t) => t.line,
             Token::String(t) => t.line,
             Token::Number(t) => t.line,
@@ -162,7 +235,10 @@ impl Token {
             | Token::Semicolon(t)
             | Token::Slash(t)
             | Token::Star(t)
+            | Token::Percent(t)
             | Token::QuestionMark(t)
+            | Token::QuestionDot(t)
+            | Token::QuestionQuestion(t)
             | Token::Colon(t)
             | Token::Bang(t)
             | Token::BangEqual(t)
@@ -171,10 +247,152 @@ impl Token {
             | Token::Greater(t)
             | Token::GreaterEqual(t)
             | Token::Less(t)
-            | Token::LessEqual(t) => t.line,
-        };
+            | Token::LessEqual(t)
+            | Token::PlusEqual(t)
+            | Token::MinusEqual(t)
+            | Token::StarEqual(t)
+            | Token::SlashEqual(t)
+            | Token::PercentEqual(t)
+            | Token::StarStar(t)
+            | Token::StarStarEqual(t)
+            | Token::PlusPlus(t)
+            | Token::MinusMinus(t)
+            | Token::Ampersand(t)
+            | Token::BitwiseOr(t)
+            | Token::Caret(t)
+            | Token::Tilde(t)
+            | Token::LeftBracket(t)
+            | Token::RightBracket(t)
+            | Token::ShiftLeft(t)
+            | Token::ShiftRight(t)
+            | Token::AmpersandEqual(t)
+            | Token::BitwiseOrEqual(t)
+            | Token::CaretEqual(t)
+            | Token::ShiftLeftEqual(t)
+            | Token::ShiftRightEqual(t)
+            | Token::AmpAmpEqual(t)
+            | Token::PipePipeEqual(t)
+            | Token::QuestionQuestionEqual(t)
+            | Token::DotDotDot(t) => t.line,
+        }
+    }
 
-        return l;
+    pub fn col(&self) -> usize {
+        match self {
+            Token::Keyword(t) => t.col,
+            Token::String(t) => t.col,
+            Token::Number(t) => t.col,
+            Token::Eof(t) => t.col,
+            Token::LeftParen(t)
+            | Token::RightParen(t)
+            | Token::LeftBrace(t)
+            | Token::RightBrace(t)
+            | Token::Comma(t)
+            | Token::Dot(t)
+            | Token::Minus(t)
+            | Token::Plus(t)
+            | Token::Semicolon(t)
+            | Token::Slash(t)
+            | Token::Star(t)
+            | Token::Percent(t)
+            | Token::QuestionMark(t)
+            | Token::QuestionDot(t)
+            | Token::QuestionQuestion(t)
+            | Token::Colon(t)
+            | Token::Bang(t)
+            | Token::BangEqual(t)
+            | Token::Equal(t)
+            | Token::EqualEqual(t)
+            | Token::Greater(t)
+            | Token::GreaterEqual(t)
+            | Token::Less(t)
+            | Token::LessEqual(t)
+            | Token::PlusEqual(t)
+            | Token::MinusEqual(t)
+            | Token::StarEqual(t)
+            | Token::SlashEqual(t)
+            | Token::PercentEqual(t)
+            | Token::StarStar(t)
+            | Token::StarStarEqual(t)
+            | Token::PlusPlus(t)
+            | Token::MinusMinus(t)
+            | Token::Ampersand(t)
+            | Token::BitwiseOr(t)
+            | Token::Caret(t)
+            | Token::Tilde(t)
+            | Token::LeftBracket(t)
+            | Token::RightBracket(t)
+            | Token::ShiftLeft(t)
+            | Token::ShiftRight(t)
+            | Token::AmpersandEqual(t)
+            | Token::BitwiseOrEqual(t)
+            | Token::CaretEqual(t)
+            | Token::ShiftLeftEqual(t)
+            | Token::ShiftRightEqual(t)
+            | Token::AmpAmpEqual(t)
+            | Token::PipePipeEqual(t)
+            | Token::QuestionQuestionEqual(t)
+            | Token::DotDotDot(t) => t.col,
+        }
+    }
+
+    pub fn lexeme(&self) -> Cow<'_, str> {
+        match self {
+            Token::Keyword(t) => t.keyword.lexeme(),
+            Token::String(t) => Cow::Borrowed(t.lexeme.as_str()),
+            Token::Number(t) => Cow::Borrowed(t.lexeme.as_str()),
+            Token::Eof(_) => Cow::Borrowed(""),
+            Token::LeftParen(t)
+            | Token::RightParen(t)
+            | Token::LeftBrace(t)
+            | Token::RightBrace(t)
+            | Token::Comma(t)
+            | Token::Dot(t)
+            | Token::Minus(t)
+            | Token::Plus(t)
+            | Token::Semicolon(t)
+            | Token::Slash(t)
+            | Token::Star(t)
+            | Token::Percent(t)
+            | Token::QuestionMark(t)
+            | Token::QuestionDot(t)
+            | Token::QuestionQuestion(t)
+            | Token::Colon(t)
+            | Token::Bang(t)
+            | Token::BangEqual(t)
+            | Token::Equal(t)
+            | Token::EqualEqual(t)
+            | Token::Greater(t)
+            | Token::GreaterEqual(t)
+            | Token::Less(t)
+            | Token::LessEqual(t)
+            | Token::PlusEqual(t)
+            | Token::MinusEqual(t)
+            | Token::StarEqual(t)
+            | Token::SlashEqual(t)
+            | Token::PercentEqual(t)
+            | Token::StarStar(t)
+            | Token::StarStarEqual(t)
+            | Token::PlusPlus(t)
+            | Token::MinusMinus(t)
+            | Token::Ampersand(t)
+            | Token::BitwiseOr(t)
+            | Token::Caret(t)
+            | Token::Tilde(t)
+            | Token::LeftBracket(t)
+            | Token::RightBracket(t)
+            | Token::ShiftLeft(t)
+            | Token::ShiftRight(t)
+            | Token::AmpersandEqual(t)
+            | Token::BitwiseOrEqual(t)
+            | Token::CaretEqual(t)
+            | Token::ShiftLeftEqual(t)
+            | Token::ShiftRightEqual(t)
+            | Token::AmpAmpEqual(t)
+            | Token::PipePipeEqual(t)
+            | Token::QuestionQuestionEqual(t)
+            | Token::DotDotDot(t) => Cow::Borrowed(t.lexeme.as_str()),
+        }
     }
 }
 
@@ -193,8 +411,17 @@ impl std::fmt::Display for Token {
             Token::Semicolon(tv) => write!(f, "Semicolon '{}'", tv.lexeme),
             Token::Slash(tv) => write!(f, "Slash '{}'", tv.lexeme),
             Token::Star(tv) => write!(f, "Star '{}'", tv.lexeme),
+            Token::Percent(tv) => write!(f, "Percent '{}'", tv.lexeme),
             Token::QuestionMark(tv) => write!(f, "QuestionMark '{}'", tv.lexeme),
+            Token::QuestionDot(tv) => write!(f, "QuestionDot '{}'", tv.lexeme),
+            Token::QuestionQuestion(tv) => write!(f, "QuestionQuestion '{}'", tv.lexeme),
             Token::Colon(tv) => write!(f, "Colon '{}'", tv.lexeme),
+            Token::Ampersand(tv) => write!(f, "Ampersand '{}'", tv.lexeme),
+            Token::BitwiseOr(tv) => write!(f, "BitwiseOr '{}'", tv.lexeme),
+            Token::Caret(tv) => write!(f, "Caret '{}'", tv.lexeme),
+            Token::Tilde(tv) => write!(f, "Tilde '{}'", tv.lexeme),
+            Token::LeftBracket(tv) => write!(f, "LeftBracket '{}'", tv.lexeme),
+            Token::RightBracket(tv) => write!(f, "RightBracket '{}'", tv.lexeme),
 
             // One or two character tokens
             Token::Bang(tv) => write!(f, "Bang '{}'", tv.lexeme),
@@ -205,9 +432,29 @@ impl std::fmt::Display for Token {
             Token::GreaterEqual(tv) => write!(f, "GreaterEqual '{}'", tv.lexeme),
             Token::Less(tv) => write!(f, "Less '{}'", tv.lexeme),
             Token::LessEqual(tv) => write!(f, "LessEqual '{}'", tv.lexeme),
+            Token::PlusEqual(tv) => write!(f, "PlusEqual '{}'", tv.lexeme),
+            Token::MinusEqual(tv) => write!(f, "MinusEqual '{}'", tv.lexeme),
+            Token::StarEqual(tv) => write!(f, "StarEqual '{}'", tv.lexeme),
+            Token::SlashEqual(tv) => write!(f, "SlashEqual '{}'", tv.lexeme),
+            Token::PercentEqual(tv) => write!(f, "PercentEqual '{}'", tv.lexeme),
+            Token::StarStar(tv) => write!(f, "StarStar '{}'", tv.lexeme),
+            Token::StarStarEqual(tv) => write!(f, "StarStarEqual '{}'", tv.lexeme),
+            Token::PlusPlus(tv) => write!(f, "PlusPlus '{}'", tv.lexeme),
+            Token::MinusMinus(tv) => write!(f, "MinusMinus '{}'", tv.lexeme),
+            Token::ShiftLeft(tv) => write!(f, "ShiftLeft '{}'", tv.lexeme),
+            Token::ShiftRight(tv) => write!(f, "ShiftRight '{}'", tv.lexeme),
+            Token::AmpersandEqual(tv) => write!(f, "AmpersandEqual '{}'", tv.lexeme),
+            Token::BitwiseOrEqual(tv) => write!(f, "BitwiseOrEqual '{}'", tv.lexeme),
+            Token::CaretEqual(tv) => write!(f, "CaretEqual '{}'", tv.lexeme),
+            Token::ShiftLeftEqual(tv) => write!(f, "ShiftLeftEqual '{}'", tv.lexeme),
+            Token::ShiftRightEqual(tv) => write!(f, "ShiftRightEqual '{}'", tv.lexeme),
+            Token::AmpAmpEqual(tv) => write!(f, "AmpAmpEqual '{}'", tv.lexeme),
+            Token::PipePipeEqual(tv) => write!(f, "PipePipeEqual '{}'", tv.lexeme),
+            Token::QuestionQuestionEqual(tv) => write!(f, "QuestionQuestionEqual '{}'", tv.lexeme),
+            Token::DotDotDot(tv) => write!(f, "DotDotDot '{}'", tv.lexeme),
 
             // Literals
-            Token::Keyword(tv) => write!(f, "Identifier '{}'", tv.lexeme),
+            Token::Keyword(tv) => write!(f, "Identifier '{}'", tv.keyword.lexeme()),
             Token::String(tv) => write!(f, "String \"{}\"", tv.value),
             Token::Number(tv) => write!(f, "Number {}", tv.value),
 
@@ -216,3 +463,16 @@ impl std::fmt::Display for Token {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_a_tagged_pointer() {
+        assert_eq!(
+            std::mem::size_of::<Token>(),
+            2 * std::mem::size_of::<usize>()
+        );
+    }
+}
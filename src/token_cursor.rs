@@ -0,0 +1,55 @@
+use crate::token::{Token, TokenValueEof};
+
+pub struct TokenCursor {
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+impl TokenCursor {
+    pub fn new(mut tokens: Vec<Token>) -> Self {
+        if !matches!(tokens.last(), Some(Token::Eof(_))) {
+            let (line, col) = tokens
+                .last()
+                .map_or((1, 1), |token| (token.line(), token.col()));
+            tokens.push(Token::Eof(Box::new(TokenValueEof { line, col })));
+        }
+
+        Self { tokens, current: 0 }
+    }
+
+    pub fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    pub fn peek_next(&self) -> &Token {
+        self.peek_nth(1)
+    }
+
+    pub fn peek_nth(&self, n: usize) -> &Token {
+        let index = (self.current + n).min(self.tokens.len() - 1);
+
+        &self.tokens[index]
+    }
+
+    pub fn previous(&self) -> &Token {
+        &self.tokens[self.current.saturating_sub(1)]
+    }
+
+    pub fn advance(&mut self) -> &Token {
+        let index = self.current;
+
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+
+        &self.tokens[index]
+    }
+
+    pub fn check(&self, predicate: impl Fn(&Token) -> bool) -> bool {
+        predicate(self.peek())
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        matches!(self.peek(), Token::Eof(_))
+    }
+}